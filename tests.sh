@@ -98,6 +98,245 @@ else
 fi
 echo
 
+# Test 5b: ver-stub show decodes the patched section without running the binary
+echo "--- Test: ver-stub show decodes patched section ---"
+OUTPUT=$($VER_STUB show ver-stub-example/target/debug/ver-stub-example.bin${EXE_SUFFIX} --format env 2>&1)
+echo "$OUTPUT"
+if echo "$OUTPUT" | grep -qE "^VER_STUB_GIT_SHA=$(git rev-parse HEAD)"; then
+    pass "ver-stub show prints git sha"
+else
+    fail "ver-stub show should print git sha, got: $OUTPUT"
+fi
+echo
+
+# Test 5c: Native backend patches without llvm-objcopy
+echo "--- Test: Patch binary with ver-stub patch --backend native ---"
+$VER_STUB --all-git --all-build-time patch --backend native \
+    ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} \
+    -o ver-stub-example/target/debug/ver-stub-example-native.bin${EXE_SUFFIX} 2>&1
+OUTPUT=$(./ver-stub-example/target/debug/ver-stub-example-native.bin${EXE_SUFFIX} 2>&1)
+if echo "$OUTPUT" | grep -q "git sha:" && ! echo "$OUTPUT" | grep -q "git sha:.*not set"; then
+    pass "native backend patches the binary"
+else
+    fail "native backend should patch the binary, got: $OUTPUT"
+fi
+echo
+
+# Test 5d: In-place patching replaces the binary itself
+echo "--- Test: Patch binary with ver-stub patch --in-place ---"
+cp ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} \
+    ver-stub-example/target/debug/ver-stub-example-inplace${EXE_SUFFIX}
+$VER_STUB --all-git patch --in-place \
+    ver-stub-example/target/debug/ver-stub-example-inplace${EXE_SUFFIX} 2>&1
+OUTPUT=$(./ver-stub-example/target/debug/ver-stub-example-inplace${EXE_SUFFIX} 2>&1)
+if echo "$OUTPUT" | grep -q "git sha:" && ! echo "$OUTPUT" | grep -q "git sha:.*not set"; then
+    pass "in-place patching works"
+else
+    fail "in-place patching should patch the binary, got: $OUTPUT"
+fi
+echo
+
+# Test 5e: ver-stub verify checks the section against expected values
+echo "--- Test: ver-stub verify ---"
+if $VER_STUB verify ver-stub-example/target/debug/ver-stub-example.bin${EXE_SUFFIX} \
+    --expect-git-sha "$(git rev-parse HEAD)" --require git_branch 2>&1; then
+    pass "ver-stub verify accepts matching values"
+else
+    fail "ver-stub verify should accept matching values"
+fi
+if $VER_STUB verify ver-stub-example/target/debug/ver-stub-example.bin${EXE_SUFFIX} \
+    --expect-git-sha 0000000000000000000000000000000000000000 2>&1; then
+    fail "ver-stub verify should reject a wrong git sha"
+else
+    pass "ver-stub verify rejects a wrong git sha"
+fi
+VER_STUB_BUILD_TIME=1500000000 $VER_STUB --git-sha --build-timestamp patch \
+    ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} \
+    -o ver-stub-example/target/debug/ver-stub-example-old.bin${EXE_SUFFIX} >/dev/null 2>&1
+if $VER_STUB verify ver-stub-example/target/debug/ver-stub-example.bin${EXE_SUFFIX} --max-build-age 1d >/dev/null 2>&1 \
+    && ! $VER_STUB verify ver-stub-example/target/debug/ver-stub-example-old.bin${EXE_SUFFIX} --max-build-age 1d >/dev/null 2>&1; then
+    pass "ver-stub verify --max-build-age rejects an old build"
+else
+    fail "ver-stub verify --max-build-age should only reject an old build"
+fi
+echo
+
+# Test 5f: ver-stub repro-diff ignores the ver_stub section
+echo "--- Test: ver-stub repro-diff ---"
+if $VER_STUB repro-diff ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} \
+    ver-stub-example/target/debug/ver-stub-example-native.bin${EXE_SUFFIX} 2>&1; then
+    pass "ver-stub repro-diff ignores the ver_stub section"
+else
+    fail "ver-stub repro-diff should report the natively patched binary as identical"
+fi
+cp ver-stub-example/target/debug/ver-stub-example-native.bin${EXE_SUFFIX} \
+    ver-stub-example/target/debug/ver-stub-example-modified.bin${EXE_SUFFIX}
+printf 'x' >> ver-stub-example/target/debug/ver-stub-example-modified.bin${EXE_SUFFIX}
+if $VER_STUB repro-diff ver-stub-example/target/debug/ver-stub-example-native.bin${EXE_SUFFIX} \
+    ver-stub-example/target/debug/ver-stub-example-modified.bin${EXE_SUFFIX} 2>&1; then
+    fail "ver-stub repro-diff should detect differences outside the ver_stub section"
+else
+    pass "ver-stub repro-diff detects differences outside the ver_stub section"
+fi
+echo
+
+# Test 5g: ver-stub zero restores the unpatched binary
+echo "--- Test: ver-stub zero ---"
+$VER_STUB zero --backend native ver-stub-example/target/debug/ver-stub-example-native.bin${EXE_SUFFIX} \
+    -o ver-stub-example/target/debug/ver-stub-example-zeroed${EXE_SUFFIX} 2>&1
+if cmp ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} \
+    ver-stub-example/target/debug/ver-stub-example-zeroed${EXE_SUFFIX}; then
+    pass "ver-stub zero restores the unpatched binary"
+else
+    fail "ver-stub zero should produce a binary identical to the unpatched one"
+fi
+echo
+
+# Test 5h: Patching again with identical contents leaves the output untouched
+echo "--- Test: Re-patching with identical contents is skipped ---"
+OUTPUT=$(VER_STUB_IDEMPOTENT=1 $VER_STUB --all-git patch --backend native \
+    ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} \
+    -o ver-stub-example/target/debug/ver-stub-example-repatch.bin${EXE_SUFFIX} 2>&1)
+OUTPUT=$(VER_STUB_IDEMPOTENT=1 $VER_STUB --all-git patch --backend native \
+    ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} \
+    -o ver-stub-example/target/debug/ver-stub-example-repatch.bin${EXE_SUFFIX} 2>&1)
+if echo "$OUTPUT" | grep -q "already up to date, skipped patching"; then
+    pass "re-patching with identical contents is skipped"
+else
+    fail "re-patching with identical contents should be skipped, got: $OUTPUT"
+fi
+VER_STUB_IDEMPOTENT=1 $VER_STUB --all-git patch ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} \
+    -o ver-stub-example/target/debug/ver-stub-example-repatch-llvm.bin${EXE_SUFFIX} >/dev/null 2>&1
+sleep 1
+touch ver-stub-example/target/debug/repatch-llvm.stamp
+OUTPUT=$(VER_STUB_IDEMPOTENT=1 $VER_STUB --all-git patch ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} \
+    -o ver-stub-example/target/debug/ver-stub-example-repatch-llvm.bin${EXE_SUFFIX} 2>&1)
+if echo "$OUTPUT" | grep -q "already up to date, skipped patching" \
+    && ! [ ver-stub-example/target/debug/ver-stub-example-repatch-llvm.bin${EXE_SUFFIX} \
+        -nt ver-stub-example/target/debug/repatch-llvm.stamp ]; then
+    pass "re-patching with the llvm backend is skipped, and leaves the modification time"
+else
+    fail "re-patching with the llvm backend should be skipped, got: $OUTPUT"
+fi
+echo
+
+# Test 5i: ver-stub usage reports free space, and a nearly full section warns
+echo "--- Test: ver-stub usage ---"
+OUTPUT=$($VER_STUB usage ver-stub-example/target/debug/ver-stub-example.bin${EXE_SUFFIX} 2>&1)
+if echo "$OUTPUT" | grep -q "^git_sha  *40 " && echo "$OUTPUT" | grep -q "^free "; then
+    pass "ver-stub usage shows member sizes and free space"
+else
+    fail "ver-stub usage should show member sizes and free space, got: $OUTPUT"
+fi
+OUTPUT=$($VER_STUB --custom "$(printf '%0400d' 0)" -o ver-stub-example/target/ver_stub_data_full 2>&1)
+if echo "$OUTPUT" | grep -q "warning: section data uses"; then
+    pass "nearly full section emits a usage warning"
+else
+    fail "nearly full section should emit a usage warning, got: $OUTPUT"
+fi
+OUTPUT=$($VER_STUB --git-sha --custom "$(printf '%0600d' 0)" --on-overflow truncate \
+    -o ver-stub-example/target/ver_stub_data_full 2>&1)
+if echo "$OUTPUT" | grep -q "dropped custom (600 bytes)"; then
+    pass "--on-overflow truncate leaves out the custom string rather than shortening it"
+else
+    fail "--on-overflow truncate should drop the custom string, got: $OUTPUT"
+fi
+echo
+
+# Test 5j: format version 2 can be found by its marker with ver-stub show --scan, the default format 1 can't
+echo "--- Test: ver-stub show --scan and --format-version 2 ---"
+$VER_STUB --git-sha --format-version 2 patch \
+    ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} \
+    -o ver-stub-example/target/debug/ver-stub-example-v2.bin${EXE_SUFFIX} 2>&1
+OUTPUT=$(./ver-stub-example/target/debug/ver-stub-example-v2.bin${EXE_SUFFIX} 2>&1)
+if echo "$OUTPUT" | grep -q "git sha:" && ! echo "$OUTPUT" | grep -q "git sha:.*not set"; then
+    pass "binary patched with format version 2 shows git sha"
+else
+    fail "binary patched with format version 2 should show git sha, got: $OUTPUT"
+fi
+OUTPUT=$($VER_STUB show --scan ver-stub-example/target/debug/ver-stub-example-v2.bin${EXE_SUFFIX} --format env 2>&1)
+if echo "$OUTPUT" | grep -qE "^VER_STUB_GIT_SHA=$(git rev-parse HEAD)"; then
+    pass "ver-stub show --scan finds the section data"
+else
+    fail "ver-stub show --scan should find the section data, got: $OUTPUT"
+fi
+if ! $VER_STUB show --scan ver-stub-example/target/debug/ver-stub-example.bin${EXE_SUFFIX} >/dev/null 2>&1; then
+    pass "format version 1 can't be found by scanning"
+else
+    fail "format version 1 should not be found by scanning"
+fi
+echo
+
+# Test 5k: Firmware images made with objcopy -O binary / ihex can be patched (ELF hosts only)
+if [ "$(uname -s)" = "Linux" ]; then
+    echo "--- Test: ver-stub patch --image ---"
+    LLVM_OBJCOPY=$(ls "$(rustc --print sysroot)"/lib/rustlib/*/bin/llvm-objcopy | head -n 1)
+    ELF=ver-stub-example/target/debug/ver-stub-example
+    IMAGES=ver-stub-example/target/debug/images
+    mkdir -p $IMAGES
+    "$LLVM_OBJCOPY" -O binary $ELF $IMAGES/fw.bin
+    "$LLVM_OBJCOPY" -O ihex $ELF $IMAGES/fw.hex
+    $VER_STUB --git-sha --format-version 2 patch --image raw --elf $ELF $IMAGES/fw.bin 2>&1
+    OUTPUT=$($VER_STUB show --scan $IMAGES/fw.patched.bin --format env 2>&1)
+    if echo "$OUTPUT" | grep -qE "^VER_STUB_GIT_SHA=$(git rev-parse HEAD)"; then
+        pass "raw image is patched at the section's address"
+    else
+        fail "raw image should be patched at the section's address, got: $OUTPUT"
+    fi
+    $VER_STUB --git-sha --format-version 2 patch --image ihex --elf $ELF $IMAGES/fw.hex 2>&1
+    OUTPUT=$($VER_STUB --git-sha --custom repatched patch --image ihex --section-size 512 \
+        $IMAGES/fw.patched.hex -o $IMAGES/fw2.hex --json 2>/dev/null)
+    if echo "$OUTPUT" | grep -q '"action": "patched"' && echo "$OUTPUT" | grep -q '"custom": "repatched"'; then
+        pass "Intel HEX image is patched again by its marker"
+    else
+        fail "Intel HEX image should be patched again by its marker, got: $OUTPUT"
+    fi
+    OUTPUT=$($VER_STUB --git-sha --custom again patch --image ihex --section-size 512 \
+        $IMAGES/fw2.hex -o $IMAGES/fw4.hex --json 2>/dev/null)
+    if echo "$OUTPUT" | grep -q '"action": "patched"' && echo "$OUTPUT" | grep -q '"custom": "again"'; then
+        pass "patching by marker keeps the marker, without --format-version"
+    else
+        fail "patching by marker should keep the marker, without --format-version, got: $OUTPUT"
+    fi
+    if ! $VER_STUB --git-sha --format-version 1 patch --image ihex --section-size 512 \
+        $IMAGES/fw2.hex -o $IMAGES/fw5.hex >/dev/null 2>&1; then
+        pass "patching by marker rejects --format-version 1"
+    else
+        fail "patching by marker should reject --format-version 1"
+    fi
+    if ! $VER_STUB --git-sha --format-version 2 patch --image ihex $IMAGES/fw.patched.hex -o $IMAGES/fw3.hex >/dev/null 2>&1; then
+        pass "patching an image by its marker requires --section-size"
+    else
+        fail "patching an image by its marker should require --section-size"
+    fi
+    echo
+fi
+
+# Test 5l: Named fields set with --field can be read at runtime and by ver-stub show
+echo "--- Test: ver-stub --field ---"
+$VER_STUB --git-sha --field deploy_env=prod --field region=eu-west-1 patch \
+    ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} \
+    -o ver-stub-example/target/debug/ver-stub-example-fields.bin${EXE_SUFFIX} 2>&1
+OUTPUT=$(./ver-stub-example/target/debug/ver-stub-example-fields.bin${EXE_SUFFIX} 2>&1)
+if echo "$OUTPUT" | grep -q "^field deploy_env: prod$" && echo "$OUTPUT" | grep -q "^field region: eu-west-1$"; then
+    pass "patched binary shows named fields"
+else
+    fail "patched binary should show named fields, got: $OUTPUT"
+fi
+OUTPUT=$($VER_STUB show --format json ver-stub-example/target/debug/ver-stub-example-fields.bin${EXE_SUFFIX} 2>&1)
+if echo "$OUTPUT" | grep -qF '"fields": {"deploy_env": "prod", "region": "eu-west-1"}'; then
+    pass "ver-stub show decodes named fields"
+else
+    fail "ver-stub show should decode named fields, got: $OUTPUT"
+fi
+if ! $VER_STUB --field =prod patch ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} \
+    -o ver-stub-example/target/debug/ver-stub-example-fields.bin${EXE_SUFFIX} >/dev/null 2>&1; then
+    pass "--field without a key is rejected"
+else
+    fail "--field without a key should be rejected"
+fi
+echo
+
 # Test 6: Build and patch objcopy example (release)
 echo "--- Test: Build and patch objcopy example (release) ---"
 (cd ver-stub-example && cargo build --release 2>&1)
@@ -157,12 +396,36 @@ else
 fi
 echo
 
-# Test 11: VER_STUB_BUFFER_SIZE=65536 should fail
-echo "--- Test: VER_STUB_BUFFER_SIZE=65536 (overflow) fails ---"
-if (cd ver-stub-example && VER_STUB_BUFFER_SIZE=65536 cargo build 2>&1); then
-    fail "VER_STUB_BUFFER_SIZE=65536 should fail"
+# Test 11: VER_STUB_BUFFER_SIZE=100000 works, with u32 offsets (format version 3)
+echo "--- Test: VER_STUB_BUFFER_SIZE=100000 (u32 offsets) works ---"
+if (cd ver-stub-example && VER_STUB_BUFFER_SIZE=100000 cargo build 2>&1); then
+    pass "VER_STUB_BUFFER_SIZE=100000 works"
+else
+    fail "VER_STUB_BUFFER_SIZE=100000 should work"
+fi
+BIG_CUSTOM=$(head -c 70000 /dev/zero | tr '\0' 'x')
+VER_STUB_BUFFER_SIZE=100000 $VER_STUB --git-sha --custom "$BIG_CUSTOM" patch \
+    ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} 2>&1
+SHOW_OUTPUT=$($VER_STUB show --format env ver-stub-example/target/debug/ver-stub-example.bin${EXE_SUFFIX} 2>&1)
+if echo "$SHOW_OUTPUT" | grep -q "^VER_STUB_CUSTOM=$BIG_CUSTOM\$" && echo "$SHOW_OUTPUT" | grep -q "^VER_STUB_GIT_SHA="; then
+    pass "70000-byte custom payload round-trips with u32 offsets"
+else
+    fail "70000-byte custom payload should round-trip with u32 offsets"
+fi
+OUTPUT=$(./ver-stub-example/target/debug/ver-stub-example.bin${EXE_SUFFIX} 2>&1)
+if echo "$OUTPUT" | grep -q "git sha:" && ! echo "$OUTPUT" | grep -q "git sha:.*not set"; then
+    pass "binary reads its section with u32 offsets"
+else
+    fail "binary should read its section with u32 offsets"
+fi
+echo
+
+# Test 11b: VER_STUB_BUFFER_SIZE=4294967296 (overflow) should fail
+echo "--- Test: VER_STUB_BUFFER_SIZE=4294967296 (overflow) fails ---"
+if (cd ver-stub-example && VER_STUB_BUFFER_SIZE=4294967296 cargo build 2>&1); then
+    fail "VER_STUB_BUFFER_SIZE=4294967296 should fail"
 else
-    pass "VER_STUB_BUFFER_SIZE=65536 correctly fails"
+    pass "VER_STUB_BUFFER_SIZE=4294967296 correctly fails"
 fi
 echo
 
@@ -204,6 +467,26 @@ else
 fi
 echo
 
+# Test 14b: SOURCE_DATE_EPOCH is used when VER_STUB_BUILD_TIME is unset
+echo "--- Test: SOURCE_DATE_EPOCH fallback ---"
+SOURCE_DATE_EPOCH=1500000000 $VER_STUB --all-git --all-build-time patch \
+    ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} 2>&1
+OUTPUT=$(./ver-stub-example/target/debug/ver-stub-example.bin${EXE_SUFFIX} 2>&1)
+if echo "$OUTPUT" | grep -q "build timestamp: 2017-07-14"; then
+    pass "SOURCE_DATE_EPOCH works (2017-07-14)"
+else
+    fail "SOURCE_DATE_EPOCH should produce 2017-07-14, got: $OUTPUT"
+fi
+VER_STUB_BUILD_TIME=1700000000 SOURCE_DATE_EPOCH=1500000000 $VER_STUB --all-git --all-build-time patch \
+    ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} 2>&1
+OUTPUT=$(./ver-stub-example/target/debug/ver-stub-example.bin${EXE_SUFFIX} 2>&1)
+if echo "$OUTPUT" | grep -q "build timestamp: 2023-11-14"; then
+    pass "VER_STUB_BUILD_TIME takes precedence over SOURCE_DATE_EPOCH"
+else
+    fail "VER_STUB_BUILD_TIME should take precedence over SOURCE_DATE_EPOCH, got: $OUTPUT"
+fi
+echo
+
 # Test 15: VER_STUB_BUILD_TIME with invalid value should fail
 echo "--- Test: VER_STUB_BUILD_TIME with invalid value fails ---"
 if VER_STUB_BUILD_TIME="not-a-timestamp" $VER_STUB --all-git --all-build-time patch \
@@ -232,6 +515,27 @@ else
 fi
 echo
 
+# Test 16b: Per-member environment overrides replace git and build time
+echo "--- Test: VER_STUB_<MEMBER> overrides ---"
+VER_STUB_GIT_SHA=0123456789abcdef VER_STUB_GIT_BRANCH=packaged VER_STUB_BUILD_DATE=2001-02-03 \
+    $VER_STUB --git-sha --git-branch --build-date patch \
+    ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} 2>&1
+OUTPUT=$(./ver-stub-example/target/debug/ver-stub-example.bin${EXE_SUFFIX} 2>&1)
+if echo "$OUTPUT" | grep -qE "git sha:\s+0123456789abcdef" \
+    && echo "$OUTPUT" | grep -qE "git branch:\s+packaged" \
+    && echo "$OUTPUT" | grep -qE "build date:\s+2001-02-03"; then
+    pass "environment overrides are used verbatim"
+else
+    fail "environment overrides should be used verbatim, got: $OUTPUT"
+fi
+if ! VER_STUB_FIELDS=deploy_env=prod $VER_STUB --git-sha --field deploy_env=placeholder patch \
+    ver-stub-example/target/debug/ver-stub-example${EXE_SUFFIX} >/dev/null 2>&1; then
+    pass "malformed VER_STUB_FIELDS is rejected"
+else
+    fail "malformed VER_STUB_FIELDS should be rejected"
+fi
+echo
+
 # Test 17: Patching updates git info without rebuild
 echo "--- Test: Patching updates git info without rebuild ---"
 
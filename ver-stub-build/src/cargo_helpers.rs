@@ -1,5 +1,6 @@
 //! Cargo build script helper functions.
 
+use crate::Error;
 use heck::ToShoutySnakeCase;
 use std::fs;
 use std::path::PathBuf;
@@ -83,7 +84,13 @@ pub fn target_profile_dir() -> PathBuf {
 /// Finds the artifact binary path using cargo's artifact dependency environment variables:
 /// `CARGO_BIN_FILE_<DEP>_<NAME>` and `CARGO_BIN_DIR_<DEP>`.
 /// See: https://doc.rust-lang.org/cargo/reference/unstable.html#artifact-dependencies
-pub fn find_artifact_binary(dep_name: &str, bin_name: &str) -> PathBuf {
+pub fn find_artifact_binary(dep_name: &str, bin_name: &str) -> Result<PathBuf, Error> {
+    let not_found = |reason: String| Error::ArtifactNotFound {
+        dep_name: dep_name.to_string(),
+        bin_name: bin_name.to_string(),
+        reason,
+    };
+
     // Convert dep name to SHOUTY_SNAKE_CASE for env var lookup.
     // Cargo converts dependency names to uppercase with dashes replaced by underscores.
     let dep_upper = dep_name.to_shouty_snake_case();
@@ -94,13 +101,13 @@ pub fn find_artifact_binary(dep_name: &str, bin_name: &str) -> PathBuf {
     if let Ok(path) = std::env::var(&file_env_var_original) {
         let path = PathBuf::from(path);
         if path.exists() {
-            return path;
+            return Ok(path);
         }
-        panic!(
-            "ver-stub-build: {} is set to '{}' but file does not exist",
+        return Err(not_found(format!(
+            "{} is set to '{}' but file does not exist",
             file_env_var_original,
             path.display()
-        );
+        )));
     }
 
     // Try CARGO_BIN_FILE_<DEP> (default binary, no name suffix)
@@ -108,13 +115,13 @@ pub fn find_artifact_binary(dep_name: &str, bin_name: &str) -> PathBuf {
     if let Ok(path) = std::env::var(&file_env_var_default) {
         let path = PathBuf::from(path);
         if path.exists() {
-            return path;
+            return Ok(path);
         }
-        panic!(
-            "ver-stub-build: {} is set to '{}' but file does not exist",
+        return Err(not_found(format!(
+            "{} is set to '{}' but file does not exist",
             file_env_var_default,
             path.display()
-        );
+        )));
     }
 
     // Try CARGO_BIN_DIR_<DEP> and search for the binary
@@ -129,19 +136,19 @@ pub fn find_artifact_binary(dep_name: &str, bin_name: &str) -> PathBuf {
                 // Match bin_name with underscores (cargo converts - to _)
                 let bin_name_underscore = bin_name.replace('-', "_");
                 if file_name_str.starts_with(&bin_name_underscore) {
-                    return entry.path();
+                    return Ok(entry.path());
                 }
             }
         }
-        panic!(
-            "ver-stub-build: {} is set to '{}' but no binary matching '{}' found in that directory",
+        return Err(not_found(format!(
+            "{} is set to '{}' but no binary matching '{}' found in that directory",
             dir_env_var, dir, bin_name
-        );
+        )));
     }
 
     // No env var found
-    panic!(
-        "ver-stub-build: could not find artifact binary for dep='{}', bin='{}'\n\
+    Err(not_found(format!(
+        "none of the artifact dependency env vars are set\n\
          Expected one of:\n\
          - {} (not set)\n\
          - {} (not set)\n\
@@ -150,6 +157,6 @@ pub fn find_artifact_binary(dep_name: &str, bin_name: &str) -> PathBuf {
          Make sure you have an artifact dependency in Cargo.toml:\n\
          [build-dependencies]\n\
          {} = {{ path = \"...\", artifact = \"bin\" }}",
-        dep_name, bin_name, file_env_var_original, file_env_var_default, dir_env_var, dep_name
-    );
+        file_env_var_original, file_env_var_default, dir_env_var, dep_name
+    )))
 }
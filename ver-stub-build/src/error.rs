@@ -5,6 +5,8 @@ use std::fmt;
 use std::io;
 use std::path::PathBuf;
 
+use crate::SectionFormat;
+
 /// Error type for ver-stub-build operations.
 #[allow(missing_docs)]
 #[derive(Debug)]
@@ -33,6 +35,38 @@ pub enum Error {
         to: PathBuf,
         source: io::Error,
     },
+
+    /// Failed to replace binary with its patched version.
+    ReplaceBinary { path: PathBuf, source: io::Error },
+
+    /// The section data doesn't fit in the section.
+    SectionTooLarge { size: usize, max: usize },
+
+    /// No version info was selected before writing the section.
+    NothingEnabled,
+
+    /// A git command failed, and `fail_on_error()` was set.
+    GitFailed { command: String, reason: String },
+
+    /// The binary of an artifact dependency could not be found.
+    ArtifactNotFound {
+        dep_name: String,
+        bin_name: String,
+        reason: String,
+    },
+
+    /// `VER_STUB_BUILD_TIME` or `SOURCE_DATE_EPOCH` could not be parsed.
+    InvalidBuildTime { var: &'static str, value: String },
+
+    /// A named field has an empty key, or its key or value contains a field separator.
+    InvalidField { key: String },
+
+    /// The member data is too large for the end offsets of the selected section format.
+    OffsetsTooNarrow { size: usize, format: SectionFormat },
+
+    /// Section format version 1 was selected for an image found by its marker, which
+    /// that format doesn't write.
+    MarkerFormatRequired,
 }
 
 impl fmt::Display for Error {
@@ -85,6 +119,81 @@ impl fmt::Display for Error {
                     source
                 )
             }
+            Error::ReplaceBinary { path, source } => {
+                write!(
+                    f,
+                    "failed to replace {} with patched binary: {}",
+                    path.display(),
+                    source
+                )
+            }
+            Error::SectionTooLarge { size, max } => {
+                write!(
+                    f,
+                    "section data too large ({} bytes, max {}). \
+                     Use with_buffer_size() or set VER_STUB_BUFFER_SIZE env var to increase, \
+                     or use with_overflow_policy() to truncate.",
+                    size, max
+                )
+            }
+            Error::NothingEnabled => {
+                write!(
+                    f,
+                    "no version info enabled. Call with_git_sha(), with_git_describe(), \
+                     with_git_branch(), with_git_commit_timestamp(), with_git_commit_date(), \
+                     with_git_commit_msg(), with_all_git(), with_build_timestamp(), with_build_date(), \
+                     with_custom(), or with_field() before writing."
+                )
+            }
+            Error::GitFailed { command, reason } => {
+                write!(f, "'{}' failed: {}", command, reason)
+            }
+            Error::ArtifactNotFound {
+                dep_name,
+                bin_name,
+                reason,
+            } => {
+                write!(
+                    f,
+                    "could not find artifact binary for dep='{}', bin='{}': {}",
+                    dep_name, bin_name, reason
+                )
+            }
+            Error::InvalidBuildTime { var, value } if *var == "SOURCE_DATE_EPOCH" => {
+                write!(f, "{} '{}' is not a valid unix timestamp", var, value)
+            }
+            Error::InvalidBuildTime { var, value } => {
+                write!(
+                    f,
+                    "{} '{}' is not a valid unix timestamp or RFC 3339 datetime",
+                    var, value
+                )
+            }
+            Error::InvalidField { key } => {
+                write!(
+                    f,
+                    "invalid field {:?}: keys must not be empty, and keys and values must not \
+                     contain the separators '\\x1e' or '\\x1f'",
+                    key
+                )
+            }
+            Error::OffsetsTooNarrow { size, format } => {
+                write!(
+                    f,
+                    "member data ({} bytes) exceeds the maximum offset {} of section format \
+                     version {}. Use with_section_format(SectionFormat::V3) for wide offsets.",
+                    size,
+                    format.max_offset(),
+                    format.version()
+                )
+            }
+            Error::MarkerFormatRequired => {
+                write!(
+                    f,
+                    "section format version 1 has no marker, so an image patched by its marker \
+                     couldn't be found again. Use format version 2 or 3, or leave it unset."
+                )
+            }
         }
     }
 }
@@ -97,6 +206,15 @@ impl StdError for Error {
             Error::GetSectionInfo { source, .. } => Some(source),
             Error::UpdateSection { source, .. } => Some(source),
             Error::CopyBinary { source, .. } => Some(source),
+            Error::ReplaceBinary { source, .. } => Some(source),
+            Error::SectionTooLarge { .. }
+            | Error::NothingEnabled
+            | Error::GitFailed { .. }
+            | Error::ArtifactNotFound { .. }
+            | Error::InvalidBuildTime { .. }
+            | Error::InvalidField { .. }
+            | Error::OffsetsTooNarrow { .. }
+            | Error::MarkerFormatRequired => None,
         }
     }
 }
@@ -0,0 +1,51 @@
+//! Reads git metadata in-process using `gix`, mirroring the output of the `git` commands.
+
+use chrono::{FixedOffset, TimeZone};
+use gix::commit::describe::SelectRef;
+
+use super::GitQuery;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Computes the same value that `git <query.args()>` would print, without the trailing newline.
+pub(super) fn query(query: GitQuery) -> Result<String, BoxError> {
+    // Like the git CLI, discover the repository from the current directory
+    let repo = gix::discover(".")?;
+
+    Ok(match query {
+        // git rev-parse HEAD
+        GitQuery::Sha => repo.head_id()?.to_string(),
+        // git describe --always --dirty
+        GitQuery::Describe => {
+            let commit = repo.head_commit()?;
+            let resolution = commit
+                .describe()
+                .names(SelectRef::AnnotatedTags)
+                .id_as_fallback(true)
+                .try_resolve()?
+                .ok_or("no names found, cannot describe anything")?;
+            resolution
+                .format_with_dirty_suffix("dirty".to_string())?
+                .to_string()
+        }
+        // git rev-parse --abbrev-ref HEAD, which prints "HEAD" when detached
+        GitQuery::Branch => match repo.head_name()? {
+            Some(name) => name.shorten().to_string(),
+            None => "HEAD".into(),
+        },
+        // git log -1 --format=%aI
+        GitQuery::CommitTimestamp => {
+            let commit = repo.head_commit()?;
+            let time = commit.author()?.time()?;
+            let offset = FixedOffset::east_opt(time.offset)
+                .ok_or_else(|| format!("invalid timezone offset {}", time.offset))?;
+            offset
+                .timestamp_opt(time.seconds, 0)
+                .single()
+                .ok_or_else(|| format!("invalid commit time {}", time.seconds))?
+                .to_rfc3339()
+        }
+        // git log -1 --format=%s
+        GitQuery::CommitSubject => repo.head_commit()?.message()?.summary().to_string(),
+    })
+}
@@ -0,0 +1,245 @@
+use crate::{Error, cargo_rerun_if, cargo_warning};
+use chrono::{DateTime, FixedOffset};
+use std::{fs, path::PathBuf, process::Command};
+
+/// In-process git backend, see [`GitBackend::Gix`].
+#[cfg(feature = "gix")]
+mod gix_backend;
+
+/// Emits cargo rerun-if-changed directives for git state files.
+/// This ensures the build script reruns when the git HEAD or refs change.
+/// Matches vergen's behavior: watches .git/HEAD and .git/<ref_path>.
+///
+/// See: https://doc.rust-lang.org/cargo/reference/build-scripts.html#rerun-if-changed
+pub fn emit_git_rerun_if_changed() {
+    // Find the git directory
+    let git_dir = match find_git_dir() {
+        Some(dir) => dir,
+        None => return,
+    };
+
+    // Always watch .git/HEAD
+    let head_path = git_dir.join("HEAD");
+    if head_path.exists() {
+        cargo_rerun_if(&format!("changed={}", head_path.display()));
+
+        // If HEAD points to a ref, also watch that ref file
+        if let Ok(head_contents) = fs::read_to_string(&head_path) {
+            let head_contents = head_contents.trim();
+            if let Some(ref_path) = head_contents.strip_prefix("ref: ") {
+                let ref_file = git_dir.join(ref_path);
+                if ref_file.exists() {
+                    cargo_rerun_if(&format!("changed={}", ref_file.display()));
+                }
+            }
+        }
+    }
+}
+
+/// Finds the .git directory by walking up from the current directory.
+fn find_git_dir() -> Option<PathBuf> {
+    let mut dir = std::env::current_dir().ok()?;
+    loop {
+        let git_dir = dir.join(".git");
+        if git_dir.is_dir() {
+            return Some(git_dir);
+        }
+        if !dir.pop() {
+            return None;
+        }
+    }
+}
+
+/// Selects how `LinkSection` collects git metadata.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GitBackend {
+    /// Run the `git` binary, once per requested value.
+    ///
+    /// This is the default.
+    #[default]
+    Cli,
+    /// Read the repository in-process using [`gix`](https://docs.rs/gix),
+    /// so that the `git` binary doesn't need to be installed.
+    ///
+    /// This produces the same values as the `git` commands used by [`GitBackend::Cli`].
+    ///
+    /// Requires the `gix` feature.
+    #[cfg(feature = "gix")]
+    Gix,
+}
+
+/// A value read from git, identified by the `git` command which produces it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GitQuery {
+    Sha,
+    Describe,
+    Branch,
+    CommitTimestamp,
+    CommitSubject,
+}
+
+impl GitQuery {
+    fn args(self) -> &'static [&'static str] {
+        match self {
+            Self::Sha => &["rev-parse", "HEAD"],
+            Self::Describe => &["describe", "--always", "--dirty"],
+            Self::Branch => &["rev-parse", "--abbrev-ref", "HEAD"],
+            Self::CommitTimestamp => &["log", "-1", "--format=%aI"],
+            Self::CommitSubject => &["log", "-1", "--format=%s"],
+        }
+    }
+}
+
+/// Gets the current git SHA using `git rev-parse HEAD`.
+pub fn get_git_sha(backend: GitBackend, fail_on_error: bool) -> Result<Option<String>, Error> {
+    run_git_query(backend, GitQuery::Sha, fail_on_error)
+}
+
+/// Gets the git describe output using `git describe --always --dirty`.
+pub fn get_git_describe(backend: GitBackend, fail_on_error: bool) -> Result<Option<String>, Error> {
+    run_git_query(backend, GitQuery::Describe, fail_on_error)
+}
+
+/// Gets the current git branch using `git rev-parse --abbrev-ref HEAD`.
+pub fn get_git_branch(backend: GitBackend, fail_on_error: bool) -> Result<Option<String>, Error> {
+    run_git_query(backend, GitQuery::Branch, fail_on_error)
+}
+
+/// Gets the git commit timestamp as a chrono DateTime.
+pub fn get_git_commit_timestamp(
+    backend: GitBackend,
+    fail_on_error: bool,
+) -> Result<Option<DateTime<FixedOffset>>, Error> {
+    // Get the author date in ISO 8601 strict format
+    let query = GitQuery::CommitTimestamp;
+    let Some(timestamp_str) = run_git_query(backend, query, fail_on_error)? else {
+        return Ok(None);
+    };
+    match DateTime::parse_from_rfc3339(&timestamp_str) {
+        Ok(dt) => Ok(Some(dt)),
+        Err(e) => git_failure(
+            command_line(backend, query),
+            format!("could not parse timestamp '{}': {}", timestamp_str, e),
+            fail_on_error,
+        ),
+    }
+}
+
+/// Gets the first line of the git commit message, truncated to 100 chars.
+pub fn get_git_commit_msg(
+    backend: GitBackend,
+    fail_on_error: bool,
+) -> Result<Option<String>, Error> {
+    let Some(msg) = run_git_query(backend, GitQuery::CommitSubject, fail_on_error)? else {
+        return Ok(None);
+    };
+    // Truncate to 100 chars to leave room in the buffer
+    Ok(Some(if msg.len() > 100 {
+        let mut end = 100;
+        while !msg.is_char_boundary(end) && end > 0 {
+            end -= 1;
+        }
+        msg[..end].to_string()
+    } else {
+        msg
+    }))
+}
+
+/// Reads a value from git using the selected backend.
+///
+/// If `fail_on_error` is true, returns an error on failure. Otherwise, emits a cargo warning
+/// and returns `Ok(None)`, allowing builds to succeed without git.
+fn run_git_query(
+    backend: GitBackend,
+    query: GitQuery,
+    fail_on_error: bool,
+) -> Result<Option<String>, Error> {
+    match backend {
+        GitBackend::Cli => run_git_command(query.args(), fail_on_error),
+        #[cfg(feature = "gix")]
+        GitBackend::Gix => match gix_backend::query(query) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) => git_failure(command_line(backend, query), e.to_string(), fail_on_error),
+        },
+    }
+}
+
+/// Runs a git command and returns stdout as a trimmed string.
+fn run_git_command(args: &[&str], fail_on_error: bool) -> Result<Option<String>, Error> {
+    let command = || format!("git {}", args.join(" "));
+    let output = match Command::new("git").args(args).output() {
+        Ok(output) => output,
+        Err(e) => {
+            return git_failure(
+                command(),
+                format!("could not execute: {}", e),
+                fail_on_error,
+            );
+        }
+    };
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return git_failure(
+            command(),
+            format!("{}: {}", output.status, stderr.trim()),
+            fail_on_error,
+        );
+    }
+
+    match String::from_utf8(output.stdout) {
+        Ok(s) => Ok(Some(s.trim().to_string())),
+        Err(_) => git_failure(command(), "output is not valid UTF-8".into(), fail_on_error),
+    }
+}
+
+/// Describes the command a backend runs for a query, for error messages.
+fn command_line(backend: GitBackend, query: GitQuery) -> String {
+    let program = match backend {
+        GitBackend::Cli => "git",
+        #[cfg(feature = "gix")]
+        GitBackend::Gix => "gix",
+    };
+    format!("{} {}", program, query.args().join(" "))
+}
+
+/// Reports a failed git command, either as an error or as a cargo warning.
+fn git_failure<T>(
+    command: String,
+    reason: String,
+    fail_on_error: bool,
+) -> Result<Option<T>, Error> {
+    let err = Error::GitFailed { command, reason };
+    if fail_on_error {
+        Err(err)
+    } else {
+        cargo_warning(&format!("ver-stub-build: {}", err));
+        Ok(None)
+    }
+}
+
+#[cfg(all(test, feature = "gix"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gix_matches_cli() {
+        // Run from within this repository's checkout. When building from a source
+        // tarball there is no checkout (or no git), so there's nothing to compare.
+        for query in [
+            GitQuery::Sha,
+            GitQuery::Describe,
+            GitQuery::Branch,
+            GitQuery::CommitTimestamp,
+            GitQuery::CommitSubject,
+        ] {
+            let Ok(cli) = run_git_query(GitBackend::Cli, query, true) else {
+                eprintln!("skipping, git failed for {query:?}");
+                return;
+            };
+            let gix = run_git_query(GitBackend::Gix, query, true).unwrap();
+            assert_eq!(cli, gix, "{query:?}");
+        }
+    }
+}
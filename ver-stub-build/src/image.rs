@@ -0,0 +1,488 @@
+//! Patching of raw, Intel HEX and Motorola S-record firmware images.
+
+use std::fmt::Write;
+use std::io;
+use std::path::PathBuf;
+
+use crate::{SectionInfo, SectionReader};
+
+/// Format of a firmware image produced from a binary, e.g. with `objcopy -O <format>`.
+///
+/// Set with [`UpdateSectionCommand::with_image`](crate::UpdateSectionCommand::with_image).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ImageFormat {
+    /// Raw memory contents (`objcopy -O binary`), starting at the image's base address.
+    Raw,
+    /// Intel HEX (`objcopy -O ihex`).
+    IntelHex,
+    /// Motorola S-record (`objcopy -O srec`).
+    SRecord,
+}
+
+impl ImageFormat {
+    /// Returns a short lowercase name for the format, e.g. `ihex`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Raw => "raw",
+            Self::IntelHex => "ihex",
+            Self::SRecord => "srec",
+        }
+    }
+}
+
+/// How to find the ver_stub section in a firmware image.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum ImageLocator {
+    /// Use the address and size of the section in the ELF file the image was made from.
+    ///
+    /// The section must be loaded at the address it runs at, which is the case for
+    /// read-only data in flash. In a raw image, the section is at its address minus the
+    /// base address of the image. Unless `base_address` is set, that is the lowest address
+    /// of any section with data in a loaded segment, which is where `objcopy -O binary`
+    /// starts the image.
+    Elf {
+        /// Path of the ELF file.
+        path: PathBuf,
+        /// Address of the first byte of a raw image. Ignored for other formats.
+        base_address: Option<u64>,
+    },
+    /// Scan the image for [`SECTION_MAGIC`](crate::SECTION_MAGIC), which is at the start of
+    /// the section data in format version 2.
+    ///
+    /// This only finds sections which have been patched before, since an unpatched section
+    /// is all zeros. The size of the section can't be read from the image, so it must be
+    /// given, and must match the `VER_STUB_BUFFER_SIZE` the firmware was built with.
+    /// A larger size would overwrite whatever follows the section.
+    ///
+    /// The new data is written in format version 2 (or 3 above 65535 bytes) unless a format
+    /// was set, so that the marker is kept; format version 1 is an error.
+    Marker {
+        /// Size of the section in bytes.
+        size: usize,
+    },
+}
+
+/// A contiguous run of bytes in an image, at an absolute address.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Segment {
+    pub(crate) address: u64,
+    pub(crate) data: Vec<u8>,
+}
+
+/// Returns the lowest address of any section with data in a loaded segment.
+pub(crate) fn elf_base_address(sections: &[(String, SectionInfo)]) -> Option<u64> {
+    sections
+        .iter()
+        .filter(|(_, info)| info.segment.is_some() && info.file_offset.is_some() && info.size > 0)
+        .map(|(_, info)| info.address)
+        .min()
+}
+
+/// Reads the memory contents of an image.
+///
+/// A raw image is a single segment at `base_address`. Records of HEX and S-record files
+/// which are contiguous are merged into one segment.
+pub(crate) fn load(
+    data: &[u8],
+    format: ImageFormat,
+    base_address: u64,
+) -> io::Result<Vec<Segment>> {
+    if format == ImageFormat::Raw {
+        return Ok(vec![Segment {
+            address: base_address,
+            data: data.to_vec(),
+        }]);
+    }
+
+    let mut segments: Vec<Segment> = Vec::new();
+    for line in Lines::new(data, format) {
+        let Some(record) = line? else {
+            continue;
+        };
+        match segments.last_mut() {
+            Some(last) if last.address + last.data.len() as u64 == record.address => {
+                last.data.extend_from_slice(&record.data);
+            }
+            _ => segments.push(Segment {
+                address: record.address,
+                data: record.data,
+            }),
+        }
+    }
+    Ok(segments)
+}
+
+/// Finds the address of the section data by scanning for the marker.
+pub(crate) fn find_marker(segments: &[Segment]) -> Option<u64> {
+    segments.iter().find_map(|segment| {
+        let reader = SectionReader::find(&segment.data)?;
+        let offset = reader.as_bytes().as_ptr() as usize - segment.data.as_ptr() as usize;
+        Some(segment.address + offset as u64)
+    })
+}
+
+/// Returns a copy of the image with `bytes` written at `address`.
+///
+/// HEX and S-record files keep their record layout and line endings; only the data and
+/// checksums of the records which hold the section are rewritten. Fails if the image
+/// doesn't contain every byte of the section.
+pub(crate) fn patch(
+    data: &[u8],
+    format: ImageFormat,
+    base_address: u64,
+    address: u64,
+    bytes: &[u8],
+) -> io::Result<Vec<u8>> {
+    let out_of_image = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "section at address {address:#x} ({} bytes) is not within the image",
+                bytes.len()
+            ),
+        )
+    };
+
+    if format == ImageFormat::Raw {
+        let start = address
+            .checked_sub(base_address)
+            .and_then(|offset| usize::try_from(offset).ok())
+            .filter(|start| start.saturating_add(bytes.len()) <= data.len())
+            .ok_or_else(out_of_image)?;
+        let mut out = data.to_vec();
+        out[start..start + bytes.len()].copy_from_slice(bytes);
+        return Ok(out);
+    }
+
+    let end = address + bytes.len() as u64;
+    let mut written = 0;
+    let mut out = Vec::with_capacity(data.len());
+    let mut lines = Lines::new(data, format);
+    while let Some(line) = lines.next() {
+        let text = lines.current;
+        let Some(mut record) = line? else {
+            out.extend_from_slice(text);
+            continue;
+        };
+        let record_end = record.address + record.data.len() as u64;
+        if record_end <= address || record.address >= end {
+            out.extend_from_slice(text);
+            continue;
+        }
+
+        // Copy the overlapping part of the section into the record
+        let from = record.address.max(address);
+        let to = record_end.min(end);
+        let dst = (from - record.address) as usize..(to - record.address) as usize;
+        let src = (from - address) as usize..(to - address) as usize;
+        record.data[dst].copy_from_slice(&bytes[src]);
+        written += (to - from) as usize;
+
+        let content_len = text.trim_ascii_end().len();
+        out.extend_from_slice(record.encode(format).as_bytes());
+        out.extend_from_slice(&text[content_len..]);
+    }
+
+    // Records may overlap in malformed files, but normally each byte is written once
+    if written < bytes.len() {
+        return Err(out_of_image());
+    }
+    Ok(out)
+}
+
+/// A data record of a HEX or S-record file.
+struct Record {
+    /// The record type: `00` for Intel HEX, or `1`, `2` or `3` for S-records.
+    kind: u8,
+    /// Absolute address of the first data byte.
+    address: u64,
+    /// The address field as written, which is relative to the extended address in Intel HEX.
+    address_field: u32,
+    data: Vec<u8>,
+}
+
+impl Record {
+    /// Encodes the record as a line (without the line ending), with a fresh checksum.
+    fn encode(&self, format: ImageFormat) -> String {
+        let mut bytes = Vec::new();
+        let mut out = String::new();
+        match format {
+            ImageFormat::IntelHex => {
+                out.push(':');
+                bytes.push(self.data.len() as u8);
+                bytes.extend_from_slice(&(self.address_field as u16).to_be_bytes());
+                bytes.push(self.kind);
+                bytes.extend_from_slice(&self.data);
+                let sum = bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+                bytes.push(sum.wrapping_neg());
+            }
+            _ => {
+                write!(out, "S{}", self.kind).unwrap();
+                let address_len = srec_address_len(self.kind);
+                bytes.push((address_len + self.data.len() + 1) as u8);
+                bytes.extend_from_slice(&self.address_field.to_be_bytes()[4 - address_len..]);
+                bytes.extend_from_slice(&self.data);
+                let sum = bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+                bytes.push(!sum);
+            }
+        }
+        for b in bytes {
+            write!(out, "{b:02X}").unwrap();
+        }
+        out
+    }
+}
+
+/// Number of address bytes in an S-record data record of the given type.
+fn srec_address_len(kind: u8) -> usize {
+    usize::from(kind) + 1
+}
+
+/// Iterates over the lines of a HEX or S-record file, parsing data records.
+///
+/// Yields `None` for lines which are not data records, e.g. the end of file record,
+/// after applying any extended address they set.
+struct Lines<'a> {
+    rest: &'a [u8],
+    format: ImageFormat,
+    line_number: usize,
+    /// Upper bits of the address, from Intel HEX extended address records.
+    extended_address: u64,
+    /// The current line, including its line ending.
+    current: &'a [u8],
+}
+
+impl<'a> Lines<'a> {
+    fn new(data: &'a [u8], format: ImageFormat) -> Self {
+        Self {
+            rest: data,
+            format,
+            line_number: 0,
+            extended_address: 0,
+            current: &[],
+        }
+    }
+
+    fn parse(&mut self, line: &[u8]) -> io::Result<Option<Record>> {
+        let invalid = |msg: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "invalid {} record on line {}: {msg}",
+                    self.format.name(),
+                    self.line_number
+                ),
+            )
+        };
+        let hex = |s: &[u8]| -> io::Result<Vec<u8>> {
+            if !s.len().is_multiple_of(2) {
+                return Err(invalid("odd number of hex digits"));
+            }
+            s.chunks(2)
+                .map(|pair| {
+                    std::str::from_utf8(pair)
+                        .ok()
+                        .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                        .ok_or_else(|| invalid("not a hex digit"))
+                })
+                .collect()
+        };
+        let check_sum = |bytes: &[u8], expected: u8| {
+            let sum = bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+            if sum == expected {
+                Ok(())
+            } else {
+                Err(invalid("checksum mismatch"))
+            }
+        };
+
+        if self.format == ImageFormat::IntelHex {
+            let Some(digits) = line.strip_prefix(b":") else {
+                return Err(invalid("expected ':'"));
+            };
+            let bytes = hex(digits)?;
+            if bytes.len() < 5 || bytes.len() != usize::from(bytes[0]) + 5 {
+                return Err(invalid("wrong length"));
+            }
+            // The checksum makes the sum of all bytes zero
+            check_sum(&bytes, 0)?;
+            let address_field = u32::from(u16::from_be_bytes([bytes[1], bytes[2]]));
+            let kind = bytes[3];
+            let data = &bytes[4..bytes.len() - 1];
+            let upper = |data: &[u8]| {
+                (data.len() == 2)
+                    .then(|| u64::from(u16::from_be_bytes([data[0], data[1]])))
+                    .ok_or_else(|| invalid("wrong length"))
+            };
+            return match kind {
+                0x00 => Ok(Some(Record {
+                    kind,
+                    address: self.extended_address + u64::from(address_field),
+                    address_field,
+                    data: data.to_vec(),
+                })),
+                // Extended segment address
+                0x02 => {
+                    self.extended_address = upper(data)? << 4;
+                    Ok(None)
+                }
+                // Extended linear address
+                0x04 => {
+                    self.extended_address = upper(data)? << 16;
+                    Ok(None)
+                }
+                0x01 | 0x03 | 0x05 => Ok(None),
+                _ => Err(invalid("unknown record type")),
+            };
+        }
+
+        let [b'S', kind, digits @ ..] = line else {
+            return Err(invalid("expected 'S'"));
+        };
+        let kind = kind.wrapping_sub(b'0');
+        if kind > 9 {
+            return Err(invalid("unknown record type"));
+        }
+        let bytes = hex(digits)?;
+        if bytes.is_empty() || bytes.len() != usize::from(bytes[0]) + 1 {
+            return Err(invalid("wrong length"));
+        }
+        // The checksum is the ones' complement of the sum of the other bytes
+        check_sum(&bytes, 0xff)?;
+        if !(1..=3).contains(&kind) {
+            return Ok(None);
+        }
+        let address_len = srec_address_len(kind);
+        if bytes.len() < address_len + 2 {
+            return Err(invalid("wrong length"));
+        }
+        let address_field = bytes[1..=address_len]
+            .iter()
+            .fold(0u32, |address, b| address << 8 | u32::from(*b));
+        Ok(Some(Record {
+            kind,
+            address: u64::from(address_field),
+            address_field,
+            data: bytes[address_len + 1..bytes.len() - 1].to_vec(),
+        }))
+    }
+}
+
+impl Iterator for Lines<'_> {
+    type Item = io::Result<Option<Record>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let len = self
+            .rest
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(self.rest.len(), |pos| pos + 1);
+        let (current, rest) = self.rest.split_at(len);
+        self.current = current;
+        self.rest = rest;
+        self.line_number += 1;
+
+        let line = current.trim_ascii();
+        if line.is_empty() {
+            return Some(Ok(None));
+        }
+        Some(self.parse(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads `len` bytes at `address`, if they are all within one segment.
+    fn read(segments: &[Segment], address: u64, len: usize) -> Option<&[u8]> {
+        segments.iter().find_map(|segment| {
+            let start = usize::try_from(address.checked_sub(segment.address)?).ok()?;
+            segment.data.get(start..start.checked_add(len)?)
+        })
+    }
+
+    // From the examples on Wikipedia
+    const HEX: &str = ":020000040800F2\r\n\
+                       :10010000214601360121470136007EFE09D2190140\r\n\
+                       :100110002146017E17C20001FF5F16002148011928\r\n\
+                       :00000001FF\r\n";
+    const SREC: &str = "S00F000068656C6C6F202020202000003C\n\
+                        S11F00007C0802A6900100049421FFF07C6C1B787C8C23783C6000003863000026\n\
+                        S5030001FB\n\
+                        S9030000FC\n";
+
+    #[test]
+    fn test_load() {
+        let segments = load(HEX.as_bytes(), ImageFormat::IntelHex, 0).unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].address, 0x0800_0100);
+        assert_eq!(segments[0].data.len(), 32);
+        assert_eq!(read(&segments, 0x0800_0110, 2), Some(&[0x21, 0x46][..]));
+        assert_eq!(read(&segments, 0x0800_011f, 2), None);
+
+        let segments = load(SREC.as_bytes(), ImageFormat::SRecord, 0).unwrap();
+        assert_eq!(segments[0].address, 0);
+        assert_eq!(segments[0].data[..4], [0x7c, 0x08, 0x02, 0xa6]);
+
+        let bad = HEX.replace(":00000001FF", ":00000001FE");
+        let err = load(bad.as_bytes(), ImageFormat::IntelHex, 0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid ihex record on line 4: checksum mismatch"
+        );
+    }
+
+    #[test]
+    fn test_patch_hex() {
+        // Spans the two data records
+        let patched = patch(
+            HEX.as_bytes(),
+            ImageFormat::IntelHex,
+            0,
+            0x0800_010e,
+            &[1, 2, 3, 4],
+        )
+        .unwrap();
+        let patched = String::from_utf8(patched).unwrap();
+        let lines: Vec<_> = patched.split_inclusive('\n').collect();
+        assert_eq!(lines[0], ":020000040800F2\r\n");
+        assert_eq!(lines[1], ":10010000214601360121470136007EFE09D2010257\r\n");
+        assert_eq!(lines[2], ":100110000304017E17C20001FF5F16002148011988\r\n");
+        assert_eq!(lines[3], ":00000001FF\r\n");
+        let segments = load(patched.as_bytes(), ImageFormat::IntelHex, 0).unwrap();
+        assert_eq!(read(&segments, 0x0800_010e, 4), Some(&[1, 2, 3, 4][..]));
+
+        assert!(
+            patch(
+                HEX.as_bytes(),
+                ImageFormat::IntelHex,
+                0,
+                0x0800_011e,
+                &[0; 4]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_patch_srec_and_raw() {
+        let patched = patch(SREC.as_bytes(), ImageFormat::SRecord, 0, 2, b"VS").unwrap();
+        let patched = String::from_utf8(patched).unwrap();
+        let segments = load(patched.as_bytes(), ImageFormat::SRecord, 0).unwrap();
+        assert_eq!(read(&segments, 0, 4), Some(&[0x7c, 0x08, b'V', b'S'][..]));
+        assert!(patched.starts_with("S00F"));
+        assert!(patched.ends_with("S5030001FB\nS9030000FC\n"));
+
+        let raw = [0u8; 16];
+        let patched = patch(&raw, ImageFormat::Raw, 0x1000, 0x100c, &[7; 4]).unwrap();
+        assert_eq!(patched[12..], [7; 4]);
+        assert!(patch(&raw, ImageFormat::Raw, 0x1000, 0x100d, &[7; 4]).is_err());
+        assert!(patch(&raw, ImageFormat::Raw, 0x1000, 0xfff, &[7; 4]).is_err());
+    }
+}
@@ -87,6 +87,18 @@ mod git_helpers;
 /// LLVM tools wrapper for section manipulation.
 mod llvm_tools;
 
+/// Patching of raw, Intel HEX and Motorola S-record firmware images.
+mod image;
+
+/// Native parsing of object file headers, to locate sections without llvm-tools.
+mod object_file;
+
+/// Fitting member data into the section when it doesn't fit.
+mod overflow;
+
+/// Structured results of patching a binary.
+mod report;
+
 /// Helper to find LLVM tools, based on code in cargo-binutils.
 mod rustc;
 
@@ -94,22 +106,31 @@ mod rustc;
 mod update_section;
 
 pub use error::Error;
-pub use llvm_tools::{BinaryFormat, LlvmTools, SectionInfo};
-pub use update_section::{UpdateSectionCommand, platform_section_name};
-pub use ver_stub::SECTION_NAME;
+pub use git_helpers::GitBackend;
+pub use image::{ImageFormat, ImageLocator};
+pub use llvm_tools::LlvmTools;
+pub use object_file::{BinaryFormat, SectionInfo, SegmentInfo, get_section_info, list_sections};
+pub use overflow::{DEFAULT_PRIORITY, OverflowPolicy};
+pub use report::{PatchAction, PatchReport, json_string};
+pub use update_section::{PatchBackend, UpdateSectionCommand, platform_section_name};
+pub use ver_stub::{
+    Describe, FIELD_SEPARATOR, Fields, KEY_VALUE_SEPARATOR, Member, SECTION_MAGIC, SECTION_NAME,
+    SectionError, SectionFormat, SectionReader, Timestamp, header_size,
+};
 
 use chrono::{DateTime, TimeZone, Utc};
 use std::{
     fs,
     path::{Path, PathBuf},
 };
-use ver_stub::{BUFFER_SIZE, Member, header_size};
+use ver_stub::BUFFER_SIZE;
 
 use cargo_helpers::{cargo_rerun_if, cargo_warning};
 use git_helpers::{
     emit_git_rerun_if_changed, get_git_branch, get_git_commit_msg, get_git_commit_timestamp,
     get_git_describe, get_git_sha,
 };
+use update_section::SectionContents;
 
 /// Builder for configuring which git information to include in version sections.
 ///
@@ -128,8 +149,14 @@ pub struct LinkSection {
     include_build_timestamp: bool,
     include_build_date: bool,
     fail_on_error: bool,
+    git_backend: GitBackend,
     custom: Option<String>,
+    fields: Vec<(String, String)>,
     buffer_size: Option<usize>,
+    usage_warning: Option<u8>,
+    overflow_policy: OverflowPolicy,
+    priority: Vec<Member>,
+    section_format: Option<SectionFormat>,
 }
 
 impl LinkSection {
@@ -210,13 +237,22 @@ impl LinkSection {
     /// building from a source tarball without `.git`), a `cargo:warning` is emitted and
     /// the corresponding data is skipped. This allows builds to succeed even without git.
     ///
-    /// When `fail_on_error()` is called, git failures will instead cause a panic,
-    /// failing the build.
+    /// When `fail_on_error()` is called, git failures will instead cause an
+    /// [`Error::GitFailed`] (or a panic, for the non-`try_` methods), failing the build.
     pub fn fail_on_error(mut self) -> Self {
         self.fail_on_error = true;
         self
     }
 
+    /// Selects how git metadata is collected.
+    ///
+    /// Defaults to [`GitBackend::Cli`], which runs the `git` binary. With the `gix` feature,
+    /// [`GitBackend::Gix`] reads the repository in-process instead.
+    pub fn with_git_backend(mut self, backend: GitBackend) -> Self {
+        self.git_backend = backend;
+        self
+    }
+
     /// Sets a custom application-specific string to embed in the binary.
     ///
     /// This can be any string your application wants to store. The total size of all
@@ -233,6 +269,62 @@ impl LinkSection {
         self
     }
 
+    /// Sets the custom string to a value serialized as JSON.
+    ///
+    /// Access it at runtime with `ver_stub::custom_as::<T>()`, with the `serde` feature of
+    /// `ver-stub` enabled. The JSON is decoded without allocating, so `T` can be used in
+    /// `no_std` binaries, and may borrow `&'static str` fields from the section.
+    ///
+    /// This replaces any string set with [`with_custom`](Self::with_custom), see there for
+    /// the size limits.
+    ///
+    /// Requires the `serde` feature.
+    ///
+    /// ```ignore
+    /// use serde::Serialize;
+    /// use ver_stub_build::LinkSection;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Deploy {
+    ///     target: &'static str,
+    ///     build_number: u32,
+    /// }
+    ///
+    /// let section = LinkSection::new().with_custom_serialized(&Deploy {
+    ///     target: "eu-west-1",
+    ///     build_number: 42,
+    /// });
+    /// ```
+    ///
+    /// # Panics
+    /// If `value` can't be serialized as JSON, e.g. a map with non-string keys.
+    #[cfg(feature = "serde")]
+    pub fn with_custom_serialized<T: serde::Serialize + ?Sized>(self, value: &T) -> Self {
+        let json = serde_json::to_string(value)
+            .unwrap_or_else(|e| panic!("ver-stub-build: can't serialize custom payload: {}", e));
+        self.with_custom(json)
+    }
+
+    /// Sets a named field to embed in the binary, e.g. `with_field("deploy_env", "prod")`.
+    ///
+    /// Can be called repeatedly to set several fields. Setting a key again replaces its
+    /// value. Keys must not be empty, and neither keys nor values can contain the
+    /// [`FIELD_SEPARATOR`] or [`KEY_VALUE_SEPARATOR`] control characters; this is checked
+    /// when the section is built.
+    ///
+    /// Fields are stored in their own member, [`Member::Fields`], so binaries built
+    /// against older versions of `ver-stub` ignore them.
+    ///
+    /// Access them at runtime with `ver_stub::field("deploy_env")` or `ver_stub::fields()`.
+    pub fn with_field(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let (key, value) = (key.into(), value.into());
+        match self.fields.iter_mut().find(|(k, _)| *k == key) {
+            Some(field) => field.1 = value,
+            None => self.fields.push((key, value)),
+        }
+        self
+    }
+
     /// Sets the buffer size for the section data.
     ///
     /// This should match the buffer size used when building the target binary.
@@ -244,6 +336,85 @@ impl LinkSection {
         self
     }
 
+    /// Sets the percentage of the buffer above which a `cargo:warning` is emitted
+    /// (default [`DEFAULT_USAGE_WARNING`], i.e. 80%).
+    ///
+    /// This gives notice that the section is filling up, e.g. because of long branch names
+    /// or commit messages, before it overflows and fails the build. Pass 100 to disable it.
+    pub fn with_usage_warning(mut self, percent: u8) -> Self {
+        self.usage_warning = Some(percent);
+        self
+    }
+
+    /// Sets what to do when the selected data doesn't fit in the section
+    /// (default [`OverflowPolicy::Error`]).
+    ///
+    /// With [`OverflowPolicy::Truncate`] or [`OverflowPolicy::Drop`], members are cut
+    /// lowest priority first (see [`with_priority`](Self::with_priority)) until the data fits,
+    /// and a `cargo:warning` lists what was cut. This way a long commit message can't fail
+    /// a release build.
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Sets the order in which members are kept when the data doesn't fit, most important first.
+    ///
+    /// Members which are not listed are less important than all listed members, and are ordered
+    /// by [`DEFAULT_PRIORITY`]. This has no effect with the default [`OverflowPolicy::Error`].
+    ///
+    /// ```
+    /// use ver_stub_build::{LinkSection, Member, OverflowPolicy};
+    ///
+    /// let section = LinkSection::new()
+    ///     .with_all_git()
+    ///     .with_overflow_policy(OverflowPolicy::Truncate)
+    ///     .with_priority([Member::GitDescribe, Member::GitSha]);
+    /// ```
+    pub fn with_priority(mut self, priority: impl IntoIterator<Item = Member>) -> Self {
+        self.priority = priority.into_iter().collect();
+        self
+    }
+
+    /// Sets the format of the section data.
+    ///
+    /// By default, this is [`SectionFormat::V1`], which every version of `ver-stub` can read,
+    /// or [`SectionFormat::V3`] if the buffer is larger than 65535 bytes, so that its u32
+    /// offsets can address all of it.
+    ///
+    /// Version 2 starts with [`SECTION_MAGIC`], so the data can be found in images without
+    /// section headers. Only opt into it if the binary was built against a version of
+    /// `ver-stub` which can read it; older versions panic on every accessor.
+    pub fn with_section_format(mut self, format: SectionFormat) -> Self {
+        self.section_format = Some(format);
+        self
+    }
+
+    /// Gets the section format to use for a buffer of the given size.
+    fn effective_section_format(&self, buffer_size: usize) -> SectionFormat {
+        self.section_format
+            .unwrap_or(if buffer_size > usize::from(u16::MAX) {
+                SectionFormat::V3
+            } else {
+                SectionFormat::V1
+            })
+    }
+
+    /// Selects a section format with a marker, for data which is found again by scanning
+    /// for it: format version 2, or 3 if the buffer is larger than 65535 bytes, unless a
+    /// format was set. Fails if format version 1 was set.
+    pub(crate) fn with_marker_format(mut self, buffer_size: usize) -> Result<Self, Error> {
+        match self.section_format {
+            Some(SectionFormat::V1) => return Err(Error::MarkerFormatRequired),
+            Some(_) => {}
+            None if buffer_size > usize::from(u16::MAX) => {
+                self.section_format = Some(SectionFormat::V3);
+            }
+            None => self.section_format = Some(SectionFormat::V2),
+        }
+        Ok(self)
+    }
+
     /// Gets the effective buffer size to use.
     fn effective_buffer_size(&self) -> usize {
         self.buffer_size
@@ -259,64 +430,123 @@ impl LinkSection {
     ///
     /// This collects all enabled version info and builds the binary section data.
     /// Does not write to any file.
+    ///
+    /// # Environment overrides
+    /// Each enabled member can be supplied through an environment variable named
+    /// `VER_STUB_<MEMBER>` (see [`member_env_var`]), e.g. `VER_STUB_GIT_SHA` or
+    /// `VER_STUB_BUILD_DATE`. If it is set, its value is used verbatim instead of running
+    /// git or reading the clock, which allows building from a source tarball without `.git`.
+    /// Setting it to an empty string leaves the member out. Overrides take precedence over
+    /// `VER_STUB_IDEMPOTENT`, `VER_STUB_BUILD_TIME` and `SOURCE_DATE_EPOCH`, and have no
+    /// effect on members which were not enabled. In particular, `VER_STUB_CUSTOM` is only
+    /// used if [`with_custom`](Self::with_custom) was called, and `VER_STUB_FIELDS` only if
+    /// [`with_field`](Self::with_field) was.
+    ///
+    /// `VER_STUB_FIELDS` is in the encoding described in [`Fields`], e.g.
+    /// `deploy_env\x1fprod\x1eregion\x1feu-west-1`, and fails with
+    /// [`Error::InvalidField`] if an entry has no key, or no key/value separator.
+    ///
+    /// # Panics
+    /// If [`try_build_section_bytes`](Self::try_build_section_bytes) returns an error.
     pub fn build_section_bytes(self) -> Vec<u8> {
-        self.check_enabled();
+        self.try_build_section_bytes()
+            .unwrap_or_else(|e| panic!("ver-stub-build: {}", e))
+    }
 
-        // Emit rerun-if-changed directives for git state (only if git data requested)
-        if self.any_git_enabled() {
-            emit_git_rerun_if_changed();
-        }
+    /// Builds the section data as bytes, returning an error instead of panicking.
+    ///
+    /// Fails if nothing was enabled, if a git command fails with `fail_on_error()` set,
+    /// if `VER_STUB_BUILD_TIME` or `SOURCE_DATE_EPOCH` is invalid, or if the data doesn't
+    /// fit in the buffer (unless an [`OverflowPolicy`] other than the default is set).
+    pub fn try_build_section_bytes(self) -> Result<Vec<u8>, Error> {
+        self.check_enabled()?;
 
         // Collect the data for each member
         let mut member_data: [Option<String>; Member::COUNT] = Default::default();
 
-        if self.include_git_sha
-            && let Some(git_sha) = get_git_sha(self.fail_on_error)
+        // Environment overrides take precedence, and the member isn't computed at all
+        let mut needed = [false; Member::COUNT];
+        for member in Member::ALL {
+            if !self.is_enabled(member) {
+                continue;
+            }
+            let var = member_env_var(member);
+            cargo_rerun_if(&format!("env-changed={}", var));
+            match std::env::var(&var) {
+                Ok(value) => {
+                    eprintln!("ver-stub-build: using {}={}", var, value);
+                    let value = match member {
+                        Member::Fields if !value.is_empty() => reencode_fields(&value)?,
+                        _ => value,
+                    };
+                    member_data[member as usize] = (!value.is_empty()).then_some(value);
+                }
+                Err(_) => needed[member as usize] = true,
+            }
+        }
+        let needed = |member: Member| needed[member as usize];
+
+        // Emit rerun-if-changed directives for git state (only if git data is needed)
+        let git_members = [
+            Member::GitSha,
+            Member::GitDescribe,
+            Member::GitBranch,
+            Member::GitCommitTimestamp,
+            Member::GitCommitDate,
+            Member::GitCommitMsg,
+        ];
+        if git_members.into_iter().any(needed) {
+            emit_git_rerun_if_changed();
+        }
+
+        if needed(Member::GitSha)
+            && let Some(git_sha) = get_git_sha(self.git_backend, self.fail_on_error)?
         {
             eprintln!("ver-stub-build: git SHA = {}", git_sha);
             member_data[Member::GitSha as usize] = Some(git_sha);
         }
 
-        if self.include_git_describe
-            && let Some(git_describe) = get_git_describe(self.fail_on_error)
+        if needed(Member::GitDescribe)
+            && let Some(git_describe) = get_git_describe(self.git_backend, self.fail_on_error)?
         {
             eprintln!("ver-stub-build: git describe = {}", git_describe);
             member_data[Member::GitDescribe as usize] = Some(git_describe);
         }
 
-        if self.include_git_branch
-            && let Some(git_branch) = get_git_branch(self.fail_on_error)
+        if needed(Member::GitBranch)
+            && let Some(git_branch) = get_git_branch(self.git_backend, self.fail_on_error)?
         {
             eprintln!("ver-stub-build: git branch = {}", git_branch);
             member_data[Member::GitBranch as usize] = Some(git_branch);
         }
 
-        if (self.include_git_commit_timestamp || self.include_git_commit_date)
-            && let Some(timestamp) = get_git_commit_timestamp(self.fail_on_error)
+        if (needed(Member::GitCommitTimestamp) || needed(Member::GitCommitDate))
+            && let Some(timestamp) = get_git_commit_timestamp(self.git_backend, self.fail_on_error)?
         {
-            if self.include_git_commit_timestamp {
+            if needed(Member::GitCommitTimestamp) {
                 let rfc3339 = timestamp.to_rfc3339();
                 eprintln!("ver-stub-build: git commit timestamp = {}", rfc3339);
                 member_data[Member::GitCommitTimestamp as usize] = Some(rfc3339);
             }
-            if self.include_git_commit_date {
+            if needed(Member::GitCommitDate) {
                 let date = timestamp.date_naive().to_string();
                 eprintln!("ver-stub-build: git commit date = {}", date);
                 member_data[Member::GitCommitDate as usize] = Some(date);
             }
         }
 
-        if self.include_git_commit_msg
-            && let Some(msg) = get_git_commit_msg(self.fail_on_error)
+        if needed(Member::GitCommitMsg)
+            && let Some(msg) = get_git_commit_msg(self.git_backend, self.fail_on_error)?
         {
             eprintln!("ver-stub-build: git commit msg = {}", msg);
             member_data[Member::GitCommitMsg as usize] = Some(msg);
         }
 
-        if self.any_build_time_enabled() {
+        if needed(Member::BuildTimestamp) || needed(Member::BuildDate) {
             // Emit rerun-if-env-changed for reproducible build options
             cargo_rerun_if("env-changed=VER_STUB_IDEMPOTENT");
             cargo_rerun_if("env-changed=VER_STUB_BUILD_TIME");
+            cargo_rerun_if("env-changed=SOURCE_DATE_EPOCH");
 
             // VER_STUB_IDEMPOTENT takes precedence: if set, never include build time
             if std::env::var("VER_STUB_IDEMPOTENT").is_ok() {
@@ -324,13 +554,13 @@ impl LinkSection {
                     "ver-stub-build: VER_STUB_IDEMPOTENT is set, skipping build timestamp/date"
                 );
             } else {
-                let build_time = get_build_time();
-                if self.include_build_timestamp {
+                let build_time = get_build_time()?;
+                if needed(Member::BuildTimestamp) {
                     let rfc3339 = build_time.to_rfc3339();
                     eprintln!("ver-stub-build: build timestamp = {}", rfc3339);
                     member_data[Member::BuildTimestamp as usize] = Some(rfc3339);
                 }
-                if self.include_build_date {
+                if needed(Member::BuildDate) {
                     let date = build_time.date_naive().to_string();
                     eprintln!("ver-stub-build: build date = {}", date);
                     member_data[Member::BuildDate as usize] = Some(date);
@@ -338,15 +568,57 @@ impl LinkSection {
             }
         }
 
-        if let Some(ref custom) = self.custom {
+        if needed(Member::Custom)
+            && let Some(ref custom) = self.custom
+        {
             eprintln!("ver-stub-build: custom = {}", custom);
             member_data[Member::Custom as usize] = Some(custom.clone());
         }
 
-        // Build the section buffer
+        if needed(Member::Fields) {
+            for (key, value) in &self.fields {
+                eprintln!("ver-stub-build: field {} = {}", key, value);
+            }
+            member_data[Member::Fields as usize] = Some(encode_fields(&self.fields)?);
+        }
+
+        // Cut members to fit, if the policy allows it
         let buffer_size = self.effective_buffer_size();
-        build_section_buffer(&member_data, buffer_size)
+        let section_format = self.effective_section_format(buffer_size);
+        let cuts = overflow::fit(
+            &mut member_data,
+            buffer_size,
+            section_format,
+            self.overflow_policy,
+            &self.priority,
+        );
+        if !cuts.is_empty() {
+            let descriptions: Vec<_> = cuts.iter().map(|cut| cut.describe()).collect();
+            cargo_warning(&format!(
+                "section data doesn't fit in {buffer_size} bytes, {}",
+                descriptions.join(", ")
+            ));
+        }
+
+        // Build the section buffer
+        let buffer = build_section_buffer(&member_data, buffer_size, section_format)?;
+
+        // Warn if the section is filling up, before it overflows (unless it already did)
+        let threshold = self.usage_warning.unwrap_or(DEFAULT_USAGE_WARNING);
+        let used = SectionReader::new(&buffer)
+            .used_len()
+            .unwrap_or(buffer_size);
+        if cuts.is_empty() && used * 100 > usize::from(threshold) * buffer_size {
+            cargo_warning(&format!(
+                "section data uses {used} of {buffer_size} bytes ({}%), more than {threshold}%; \
+                 consider increasing VER_STUB_BUFFER_SIZE",
+                used * 100 / buffer_size
+            ));
+        }
+
+        Ok(buffer)
     }
+
     /// Writes the section data file to the specified path.
     ///
     /// If the path is a directory, writes to `{path}/ver_stub_data`.
@@ -400,10 +672,12 @@ impl LinkSection {
     /// * `binary_path` - Path to the binary to patch
     pub fn patch_into(self, binary_path: impl AsRef<Path>) -> UpdateSectionCommand {
         UpdateSectionCommand {
-            link_section: self,
+            contents: SectionContents::Data(self),
             bin_path: binary_path.as_ref().to_path_buf(),
             new_name: None,
             dry_run: false,
+            backend: PatchBackend::default(),
+            image: None,
         }
     }
 
@@ -416,9 +690,23 @@ impl LinkSection {
     /// # Arguments
     /// * `dep_name` - The name of the dependency as specified in Cargo.toml
     /// * `bin_name` - The name of the binary within the dependency
+    ///
+    /// # Panics
+    /// If the binary can't be found, see [`try_patch_into_bin_dep`](Self::try_patch_into_bin_dep).
     pub fn patch_into_bin_dep(self, dep_name: &str, bin_name: &str) -> UpdateSectionCommand {
-        let bin_path = cargo_helpers::find_artifact_binary(dep_name, bin_name);
-        self.patch_into(bin_path)
+        self.try_patch_into_bin_dep(dep_name, bin_name)
+            .unwrap_or_else(|e| panic!("ver-stub-build: {}", e))
+    }
+
+    /// Like [`patch_into_bin_dep`](Self::patch_into_bin_dep), but returns
+    /// [`Error::ArtifactNotFound`] instead of panicking if the binary can't be found.
+    pub fn try_patch_into_bin_dep(
+        self,
+        dep_name: &str,
+        bin_name: &str,
+    ) -> Result<UpdateSectionCommand, Error> {
+        let bin_path = cargo_helpers::find_artifact_binary(dep_name, bin_name)?;
+        Ok(self.patch_into(bin_path))
     }
 
     fn any_git_enabled(&self) -> bool {
@@ -434,19 +722,34 @@ impl LinkSection {
         self.include_build_timestamp || self.include_build_date
     }
 
-    fn check_enabled(&self) {
-        if !self.any_git_enabled() && !self.any_build_time_enabled() && self.custom.is_none() {
-            panic!(
-                "ver-stub-build: no version info enabled. Call with_git_sha(), with_git_describe(), \
-                 with_git_branch(), with_git_commit_timestamp(), with_git_commit_date(), \
-                 with_git_commit_msg(), with_all_git(), with_build_timestamp(), with_build_date(), \
-                 or with_custom() before writing."
-            );
+    fn is_enabled(&self, member: Member) -> bool {
+        match member {
+            Member::GitSha => self.include_git_sha,
+            Member::GitDescribe => self.include_git_describe,
+            Member::GitBranch => self.include_git_branch,
+            Member::GitCommitTimestamp => self.include_git_commit_timestamp,
+            Member::GitCommitDate => self.include_git_commit_date,
+            Member::GitCommitMsg => self.include_git_commit_msg,
+            Member::BuildTimestamp => self.include_build_timestamp,
+            Member::BuildDate => self.include_build_date,
+            Member::Custom => self.custom.is_some(),
+            Member::Fields => !self.fields.is_empty(),
         }
     }
 
+    fn check_enabled(&self) -> Result<(), Error> {
+        if !self.any_git_enabled()
+            && !self.any_build_time_enabled()
+            && self.custom.is_none()
+            && self.fields.is_empty()
+        {
+            return Err(Error::NothingEnabled);
+        }
+        Ok(())
+    }
+
     pub(crate) fn write_section_to_path(self, path: &Path) -> Result<PathBuf, Error> {
-        let buffer = self.build_section_bytes();
+        let buffer = self.try_build_section_bytes()?;
 
         // Write to file - if path is a directory, append ver_stub_data
         let output_path = if path.is_dir() {
@@ -463,14 +766,66 @@ impl LinkSection {
     }
 }
 
+/// Default percentage of the buffer above which a warning is emitted,
+/// see [`LinkSection::with_usage_warning`].
+pub const DEFAULT_USAGE_WARNING: u8 = 80;
+
+/// Returns the name of the environment variable which overrides a member,
+/// e.g. `VER_STUB_GIT_SHA` for [`Member::GitSha`].
+///
+/// See [`LinkSection::build_section_bytes`] for how overrides are applied.
+pub fn member_env_var(member: Member) -> String {
+    format!("VER_STUB_{}", member.name().to_uppercase())
+}
+
+/// Encodes named fields as the data of [`Member::Fields`], see [`Fields`].
+fn encode_fields(fields: &[(String, String)]) -> Result<String, Error> {
+    let mut data = String::new();
+    for (key, value) in fields {
+        let has_separator = |s: &str| s.contains([FIELD_SEPARATOR, KEY_VALUE_SEPARATOR]);
+        if key.is_empty() || has_separator(key) || has_separator(value) {
+            return Err(Error::InvalidField { key: key.clone() });
+        }
+        if !data.is_empty() {
+            data.push(FIELD_SEPARATOR);
+        }
+        data.push_str(key);
+        data.push(KEY_VALUE_SEPARATOR);
+        data.push_str(value);
+    }
+    Ok(data)
+}
+
+/// Checks the value of the `VER_STUB_FIELDS` override, which is in the encoding written
+/// by [`encode_fields`] (as printed by `ver-stub show --format env`), by decoding it and
+/// encoding it again. This way malformed fields fail the build, rather than being misread
+/// at runtime.
+fn reencode_fields(data: &str) -> Result<String, Error> {
+    let fields = data
+        .split(FIELD_SEPARATOR)
+        .map(|entry| {
+            entry
+                .split_once(KEY_VALUE_SEPARATOR)
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .ok_or_else(|| Error::InvalidField {
+                    key: entry.to_string(),
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    encode_fields(&fields)
+}
+
 /// Builds the section buffer from member data.
 ///
-/// Format:
-/// - First byte: number of members (Member::COUNT) for forward compatibility
-/// - Next `Member::COUNT * 2` bytes: header with end offsets (u16, little-endian, relative to header)
+/// Format (see [`SectionFormat`]):
+/// - Version 2 and later: [`SECTION_MAGIC`], then the format version byte
+/// - Next byte: number of members (Member::COUNT) for forward compatibility
+/// - Next `Member::COUNT * offset_size` bytes: header with end offsets (u16, or u32 in version 3,
+///   little-endian, relative to header)
 /// - Remaining bytes: concatenated string data
 ///
-/// Header size = 1 + Member::COUNT * 2
+/// Header size = 10 + Member::COUNT * 2 in version 2, 10 + Member::COUNT * 4 in version 3,
+/// 1 + Member::COUNT * 2 in version 1
 ///
 /// For member N:
 /// - start = header_size + end[N-1] if N > 0, else header_size
@@ -482,12 +837,28 @@ impl LinkSection {
 fn build_section_buffer(
     member_data: &[Option<String>; Member::COUNT],
     buffer_size: usize,
-) -> Vec<u8> {
+    format: SectionFormat,
+) -> Result<Vec<u8>, Error> {
+    let header_sz = format.header_size(Member::COUNT);
+    if header_sz > buffer_size {
+        return Err(Error::SectionTooLarge {
+            size: header_sz,
+            max: buffer_size,
+        });
+    }
     let mut buffer = vec![0u8; buffer_size];
-    let header_sz = header_size(Member::COUNT);
 
-    // First byte: number of members
-    buffer[0] = Member::COUNT as u8;
+    // Magic and format version, so the section can be found without section headers
+    let mut pos = 0;
+    if format != SectionFormat::V1 {
+        buffer[..SECTION_MAGIC.len()].copy_from_slice(&SECTION_MAGIC);
+        buffer[SECTION_MAGIC.len()] = format.version();
+        pos = SECTION_MAGIC.len() + 1;
+    }
+
+    // Next byte: number of members
+    buffer[pos] = Member::COUNT as u8;
+    pos += 1;
 
     // Data starts after the header; track position relative to header_size
     let mut relative_offset: usize = 0;
@@ -499,74 +870,91 @@ fn build_section_buffer(
             let absolute_end = absolute_start + bytes.len();
 
             if absolute_end > buffer_size {
-                panic!(
-                    "ver-stub-build: section data too large ({} bytes, max {}). \
-                     Use with_buffer_size() or set VER_STUB_BUFFER_SIZE env var to increase.",
-                    absolute_end, buffer_size
-                );
+                return Err(Error::SectionTooLarge {
+                    size: absolute_end,
+                    max: buffer_size,
+                });
             }
 
             // Write the data
             buffer[absolute_start..absolute_end].copy_from_slice(bytes);
 
             relative_offset += bytes.len();
+            if relative_offset > format.max_offset() {
+                return Err(Error::OffsetsTooNarrow {
+                    size: relative_offset,
+                    format,
+                });
+            }
         }
 
         // Write the end offset for this member (relative to header_size)
         // If member is not present, end == previous end, so start == end indicates "not present"
-        // Offset positions start at byte 1 (after the num_members byte)
-        let header_offset = 1 + idx * 2;
-        buffer[header_offset..header_offset + 2]
-            .copy_from_slice(&(relative_offset as u16).to_le_bytes());
+        // Offset positions start after the num_members byte
+        let offset_size = format.offset_size();
+        let header_offset = pos + idx * offset_size;
+        buffer[header_offset..header_offset + offset_size]
+            .copy_from_slice(&(relative_offset as u32).to_le_bytes()[..offset_size]);
     }
 
-    buffer
+    Ok(buffer)
 }
 
 // ============================================================================
 // Helper functions
 // ============================================================================
 
-/// Gets the build time, either from VER_STUB_BUILD_TIME env var or Utc::now().
+/// Gets the build time, from the first of these which is set:
+/// 1. `VER_STUB_BUILD_TIME`
+/// 2. `SOURCE_DATE_EPOCH` (see <https://reproducible-builds.org/specs/source-date-epoch/>)
+/// 3. `Utc::now()`
 ///
-/// If VER_STUB_BUILD_TIME is set, it tries to parse it as:
+/// The env var is parsed as:
 /// 1. An integer (unix timestamp in seconds)
-/// 2. An RFC 3339 datetime string
+/// 2. An RFC 3339 datetime string, for `VER_STUB_BUILD_TIME` only, since the reproducible
+///    builds spec defines `SOURCE_DATE_EPOCH` as an integer
 ///
 /// This supports reproducible builds by allowing a fixed build time.
-fn get_build_time() -> DateTime<Utc> {
-    if let Ok(val) = std::env::var("VER_STUB_BUILD_TIME") {
-        // Try parsing as unix timestamp (integer) first
-        if let Ok(ts) = val.parse::<i64>() {
-            let dt = Utc.timestamp_opt(ts, 0).single().unwrap_or_else(|| {
-                panic!(
-                    "ver-stub-build: VER_STUB_BUILD_TIME '{}' is not a valid unix timestamp",
-                    val
-                )
-            });
-            eprintln!(
-                "ver-stub-build: using VER_STUB_BUILD_TIME={} (unix timestamp), overriding Utc::now()",
-                val
-            );
-            return dt;
+fn get_build_time() -> Result<DateTime<Utc>, Error> {
+    for var in ["VER_STUB_BUILD_TIME", "SOURCE_DATE_EPOCH"] {
+        if let Ok(val) = std::env::var(var) {
+            return parse_build_time(var, val);
         }
+    }
 
-        // Try parsing as RFC 3339
-        if let Ok(dt) = DateTime::parse_from_rfc3339(&val) {
-            eprintln!(
-                "ver-stub-build: using VER_STUB_BUILD_TIME={} (RFC 3339), overriding Utc::now()",
-                val
-            );
-            return dt.with_timezone(&Utc);
-        }
+    Ok(Utc::now())
+}
 
-        panic!(
-            "ver-stub-build: VER_STUB_BUILD_TIME '{}' is not a valid unix timestamp or RFC 3339 datetime",
-            val
+/// Parses the value of a build time env var.
+fn parse_build_time(var: &'static str, val: String) -> Result<DateTime<Utc>, Error> {
+    // Try parsing as unix timestamp (integer) first
+    if let Ok(ts) = val.parse::<i64>() {
+        let dt = Utc
+            .timestamp_opt(ts, 0)
+            .single()
+            .ok_or_else(|| Error::InvalidBuildTime {
+                var,
+                value: val.clone(),
+            })?;
+        eprintln!(
+            "ver-stub-build: using {}={} (unix timestamp), overriding Utc::now()",
+            var, val
         );
+        return Ok(dt);
     }
 
-    Utc::now()
+    // Try parsing as RFC 3339, which SOURCE_DATE_EPOCH doesn't allow
+    if var == "VER_STUB_BUILD_TIME"
+        && let Ok(dt) = DateTime::parse_from_rfc3339(&val)
+    {
+        eprintln!(
+            "ver-stub-build: using {}={} (RFC 3339), overriding Utc::now()",
+            var, val
+        );
+        return Ok(dt.with_timezone(&Utc));
+    }
+
+    Err(Error::InvalidBuildTime { var, value: val })
 }
 
 #[cfg(test)]
@@ -579,39 +967,208 @@ mod tests {
 
         args[0] = Some("asdf".into());
 
-        let buf_vec = build_section_buffer(&args, BUFFER_SIZE);
-        let buffer: &[u8; BUFFER_SIZE] = (&buf_vec[..]).try_into().unwrap();
+        let buffer = build_section_buffer(&args, BUFFER_SIZE, SectionFormat::V2).unwrap();
+        let reader = SectionReader::new(&buffer);
 
-        assert_eq!(Member::get_idx_from_buffer(0, &buffer).unwrap(), "asdf");
+        assert_eq!(reader.get_idx(0).unwrap().unwrap(), "asdf");
         for idx in 1..Member::COUNT {
-            assert!(Member::get_idx_from_buffer(idx, &buffer).is_none());
+            assert!(reader.get_idx(idx).unwrap().is_none());
         }
 
         args[2] = Some("jkl;".into());
 
-        let buf_vec = build_section_buffer(&args, BUFFER_SIZE);
-        let buffer: &[u8; BUFFER_SIZE] = (&buf_vec[..]).try_into().unwrap();
+        let buffer = build_section_buffer(&args, BUFFER_SIZE, SectionFormat::V2).unwrap();
+        let reader = SectionReader::new(&buffer);
 
-        assert_eq!(Member::get_idx_from_buffer(0, &buffer).unwrap(), "asdf");
-        assert!(Member::get_idx_from_buffer(1, &buffer).is_none());
-        assert_eq!(Member::get_idx_from_buffer(2, &buffer).unwrap(), "jkl;");
+        assert_eq!(reader.get_idx(0).unwrap().unwrap(), "asdf");
+        assert!(reader.get_idx(1).unwrap().is_none());
+        assert_eq!(reader.get_idx(2).unwrap().unwrap(), "jkl;");
         for idx in 3..Member::COUNT {
-            assert!(Member::get_idx_from_buffer(idx, &buffer).is_none());
+            assert!(reader.get_idx(idx).unwrap().is_none());
         }
 
         args[5] = Some("nana".into());
 
-        let buf_vec = build_section_buffer(&args, BUFFER_SIZE);
-        let buffer: &[u8; BUFFER_SIZE] = (&buf_vec[..]).try_into().unwrap();
+        let buffer = build_section_buffer(&args, BUFFER_SIZE, SectionFormat::V2).unwrap();
+        let reader = SectionReader::new(&buffer);
 
-        assert_eq!(Member::get_idx_from_buffer(0, &buffer).unwrap(), "asdf");
-        assert!(Member::get_idx_from_buffer(1, &buffer).is_none());
-        assert_eq!(Member::get_idx_from_buffer(2, &buffer).unwrap(), "jkl;");
-        assert!(Member::get_idx_from_buffer(3, &buffer).is_none());
-        assert!(Member::get_idx_from_buffer(4, &buffer).is_none());
-        assert_eq!(Member::get_idx_from_buffer(5, &buffer).unwrap(), "nana");
+        assert_eq!(reader.get_idx(0).unwrap().unwrap(), "asdf");
+        assert!(reader.get_idx(1).unwrap().is_none());
+        assert_eq!(reader.get_idx(2).unwrap().unwrap(), "jkl;");
+        assert!(reader.get_idx(3).unwrap().is_none());
+        assert!(reader.get_idx(4).unwrap().is_none());
+        assert_eq!(reader.get_idx(5).unwrap().unwrap(), "nana");
         for idx in 6..Member::COUNT {
-            assert!(Member::get_idx_from_buffer(idx, &buffer).is_none());
+            assert!(reader.get_idx(idx).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn test_build_section_buffer_too_large() {
+        let mut args = [const { None }; Member::COUNT];
+        args[0] = Some("a".repeat(16));
+
+        for format in [SectionFormat::V1, SectionFormat::V2, SectionFormat::V3] {
+            let header_sz = format.header_size(Member::COUNT);
+            assert!(build_section_buffer(&args, header_sz + 16, format).is_ok());
+            assert!(matches!(
+                build_section_buffer(&args, header_sz + 15, format),
+                Err(Error::SectionTooLarge { size, max }) if size == header_sz + 16 && max == header_sz + 15
+            ));
+        }
+    }
+
+    #[test]
+    fn test_build_section_buffer_formats() {
+        let mut args = [const { None }; Member::COUNT];
+        args[0] = Some("asdf".into());
+
+        let buffer = build_section_buffer(&args, 64, SectionFormat::V2).unwrap();
+        assert_eq!(buffer[..8], SECTION_MAGIC);
+        assert_eq!(buffer[8], 2);
+        let reader = SectionReader::new(&buffer);
+        assert_eq!(reader.format(), Ok(Some(SectionFormat::V2)));
+        assert_eq!(reader.get(Member::GitSha), Ok(Some("asdf")));
+        assert_eq!(
+            SectionReader::find(&buffer).unwrap().used_len(),
+            Ok(SectionFormat::V2.header_size(Member::COUNT) + 4)
+        );
+
+        let buffer = build_section_buffer(&args, 64, SectionFormat::V1).unwrap();
+        assert_eq!(buffer[0], Member::COUNT as u8);
+        let reader = SectionReader::new(&buffer);
+        assert_eq!(reader.format(), Ok(Some(SectionFormat::V1)));
+        assert_eq!(reader.get(Member::GitSha), Ok(Some("asdf")));
+        assert!(SectionReader::find(&buffer).is_none());
+    }
+
+    #[test]
+    fn test_build_section_buffer_wide() {
+        let mut args = [const { None }; Member::COUNT];
+        args[0] = Some("asdf".into());
+        args[Member::Custom as usize] = Some("x".repeat(70_000));
+        let buffer_size = 80_000;
+
+        let buffer = build_section_buffer(&args, buffer_size, SectionFormat::V3).unwrap();
+        let reader = SectionReader::new(&buffer);
+        assert_eq!(reader.format(), Ok(Some(SectionFormat::V3)));
+        assert_eq!(reader.get(Member::GitSha), Ok(Some("asdf")));
+        assert_eq!(reader.get(Member::Custom).unwrap().unwrap().len(), 70_000);
+
+        assert!(matches!(
+            build_section_buffer(&args, buffer_size, SectionFormat::V2),
+            Err(Error::OffsetsTooNarrow {
+                size: 70_004,
+                format: SectionFormat::V2
+            })
+        ));
+
+        let section = LinkSection::new();
+        assert_eq!(section.effective_section_format(65_535), SectionFormat::V1);
+        assert_eq!(section.effective_section_format(65_536), SectionFormat::V3);
+        let section = section.with_section_format(SectionFormat::V2);
+        assert_eq!(section.effective_section_format(65_536), SectionFormat::V2);
+    }
+
+    #[test]
+    fn test_parse_build_time() {
+        let parse = |var, val: &str| parse_build_time(var, val.to_string()).ok();
+        let expected = Utc.timestamp_opt(1_500_000_000, 0).single();
+        assert_eq!(parse("VER_STUB_BUILD_TIME", "1500000000"), expected);
+        assert_eq!(parse("SOURCE_DATE_EPOCH", "1500000000"), expected);
+        assert_eq!(
+            parse("VER_STUB_BUILD_TIME", "2017-07-14T02:40:00Z"),
+            expected
+        );
+        assert_eq!(parse("SOURCE_DATE_EPOCH", "2017-07-14T02:40:00Z"), None);
+        assert_eq!(parse("SOURCE_DATE_EPOCH", "yesterday"), None);
+    }
+
+    #[test]
+    fn test_with_marker_format() {
+        let marked = |section: LinkSection, buffer_size| {
+            section
+                .with_marker_format(buffer_size)
+                .map(|section| section.effective_section_format(buffer_size))
+        };
+        assert!(matches!(
+            marked(LinkSection::new(), 512),
+            Ok(SectionFormat::V2)
+        ));
+        assert!(matches!(
+            marked(LinkSection::new(), 65_536),
+            Ok(SectionFormat::V3)
+        ));
+
+        let section = LinkSection::new().with_section_format(SectionFormat::V3);
+        assert!(matches!(marked(section, 512), Ok(SectionFormat::V3)));
+        let section = LinkSection::new().with_section_format(SectionFormat::V1);
+        assert!(matches!(
+            marked(section, 512),
+            Err(Error::MarkerFormatRequired)
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_custom_serialized() {
+        #[derive(serde::Serialize)]
+        struct Deploy<'a> {
+            target: &'a str,
+            build_number: u32,
+        }
+
+        let buffer = LinkSection::new()
+            .with_custom("replaced")
+            .with_custom_serialized(&Deploy {
+                target: "eu \"west\"",
+                build_number: 42,
+            })
+            .build_section_bytes();
+        assert_eq!(
+            SectionReader::new(&buffer).get(Member::Custom),
+            Ok(Some(r#"{"target":"eu \"west\"","build_number":42}"#))
+        );
+    }
+
+    #[test]
+    fn test_fields() {
+        let section = LinkSection::new()
+            .with_field("deploy_env", "staging")
+            .with_field("region", "eu-west-1")
+            .with_field("deploy_env", "prod");
+        let buffer = section.build_section_bytes();
+        let reader = SectionReader::new(&buffer);
+        let fields: Vec<_> = reader.fields().unwrap().collect();
+        assert_eq!(fields, [("deploy_env", "prod"), ("region", "eu-west-1")]);
+        assert_eq!(reader.get(Member::Custom), Ok(None));
+
+        for (key, value) in [("", "x"), ("a\x1fb", "x"), ("a", "x\x1ey")] {
+            let result = LinkSection::new()
+                .with_field(key, value)
+                .try_build_section_bytes();
+            assert!(matches!(result, Err(Error::InvalidField { .. })), "{key:?}");
+        }
+    }
+
+    #[test]
+    fn test_reencode_fields() {
+        let data = "deploy_env\x1fprod\x1eregion\x1feu-west-1\x1eempty\x1f";
+        assert_eq!(reencode_fields(data).unwrap(), data);
+
+        for data in ["deploy_env=prod", "\x1fprod", "a\x1f1\x1e", "a\x1f1\x1fx"] {
+            assert!(
+                matches!(reencode_fields(data), Err(Error::InvalidField { .. })),
+                "{data:?}"
+            );
         }
     }
+
+    #[test]
+    fn test_nothing_enabled() {
+        assert!(matches!(
+            LinkSection::new().try_build_section_bytes(),
+            Err(Error::NothingEnabled)
+        ));
+    }
 }
@@ -1,37 +1,14 @@
 //! LLVM tools wrapper for section manipulation.
 
-mod parsing;
-
 use std::env::consts::EXE_SUFFIX;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use crate::object_file::{self, BinaryFormat, SectionInfo};
 use crate::rustc;
-use parsing::{parse_coff_sections, parse_elf_sections, parse_macho_sections};
-
-/// Binary format detected from llvm-readobj output.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum BinaryFormat {
-    /// ELF
-    Elf,
-    /// Mach-O
-    MachO,
-    /// PE/COFF
-    Coff,
-}
-
-/// Information about a section in a binary.
-#[derive(Debug, Clone)]
-#[non_exhaustive]
-pub struct SectionInfo {
-    /// Size of the section in bytes.
-    pub size: usize,
-    /// Whether the section is writable (has SHF_WRITE on ELF, or is in __DATA segment on Mach-O).
-    pub is_writable: bool,
-}
 
-/// Wrapper for LLVM tools (llvm-readobj, llvm-objcopy).
+/// Wrapper for LLVM tools (llvm-objcopy).
 ///
 /// This provides access to LLVM tools from the Rust toolchain for reading
 /// and modifying ELF sections in binaries.
@@ -63,63 +40,22 @@ impl LlvmTools {
     /// Returns:
     /// Ok((binary_format, section_name, section_info_if_found))
     /// Returns `Ok(Some(SectionInfo))` if the section exists, `Ok(None)` if it doesn't,
-    /// or `Err` if there was an error executing llvm-readobj or parsing the output.
+    /// or `Err` if the file could not be read or parsed.
+    ///
+    /// This parses the object file headers natively (see [`crate::get_section_info`]),
+    /// rather than running `llvm-readobj`, so it doesn't depend on its text output format.
     pub fn get_section_info(
         &self,
         bin: impl AsRef<Path>,
         section_name_fn: impl FnOnce(BinaryFormat) -> io::Result<String>,
     ) -> io::Result<(BinaryFormat, String, Option<SectionInfo>)> {
-        let bin = bin.as_ref();
-        let readobj_path = self.bin_dir.join(format!("llvm-readobj{}", EXE_SUFFIX));
-
-        let mut cmd = Command::new(&readobj_path);
-        cmd.arg("--sections").arg(bin);
-
-        if self.dry_run {
-            eprintln!("{cmd:#?}");
-        }
-
-        let output = cmd.output()?;
-
-        if !output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            eprintln!("llvm-readobj failed with status {}", output.status);
-            eprintln!("stdout:\n{}", stdout);
-            eprintln!("stderr:\n{}", stderr);
-            return Err(io::Error::other(format!(
-                "llvm-readobj failed with status {}",
-                output.status
-            )));
-        }
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-
-        let binary_format = BinaryFormat::detect(&stdout).ok_or_else(|| {
-            eprintln!("Could not detect binary format. llvm-readobj output:");
-            eprintln!("{}", stdout);
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                "could not detect binary format from llvm-readobj output",
-            )
-        })?;
-
-        let section_name = section_name_fn(binary_format)?;
-
-        // Detect binary format and dispatch to appropriate parser
-        let section_info = match binary_format {
-            BinaryFormat::Elf => parse_elf_sections(&stdout, &section_name),
-            BinaryFormat::MachO => parse_macho_sections(&stdout, &section_name),
-            BinaryFormat::Coff => parse_coff_sections(&stdout, &section_name),
-        }?;
-
-        Ok((binary_format, section_name, section_info))
+        object_file::get_section_info(bin, section_name_fn)
     }
 
     /// Gets the size of a section in a binary.
     ///
     /// Returns `Ok(Some(size))` if the section exists, `Ok(None)` if it doesn't,
-    /// or `Err` if there was an error executing llvm-readobj or parsing the output.
+    /// or `Err` if the file could not be read or parsed.
     ///
     /// This is a convenience wrapper around `get_section_info` that returns only the size.
     pub fn get_section_size(
@@ -0,0 +1,227 @@
+//! ELF section and program header parsing.
+
+use std::io;
+
+use super::{Bytes, SectionInfo, SegmentInfo, checked_add, malformed, table_entry, to_usize};
+
+const ELFCLASS32: u8 = 1;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const ELFDATA2MSB: u8 = 2;
+
+const SHN_XINDEX: u16 = 0xffff;
+const SHT_NOBITS: u32 = 8;
+const SHF_WRITE: u64 = 0x1;
+const SHF_ALLOC: u64 = 0x2;
+
+const PT_LOAD: u32 = 1;
+const PF_W: u32 = 0x2;
+
+/// Field offsets which differ between ELF32 and ELF64.
+struct Layout {
+    e_phoff: u64,
+    e_shoff: u64,
+    e_phentsize: u64,
+    e_phnum: u64,
+    e_shentsize: u64,
+    e_shnum: u64,
+    e_shstrndx: u64,
+    sh_flags: u64,
+    sh_addr: u64,
+    sh_offset: u64,
+    sh_size: u64,
+    sh_link: u64,
+    sh_addralign: u64,
+    min_shentsize: u16,
+    p_flags: u64,
+    p_vaddr: u64,
+    p_memsz: u64,
+    min_phentsize: u16,
+}
+
+const LAYOUT_32: Layout = Layout {
+    e_phoff: 0x1c,
+    e_shoff: 0x20,
+    e_phentsize: 0x2a,
+    e_phnum: 0x2c,
+    e_shentsize: 0x2e,
+    e_shnum: 0x30,
+    e_shstrndx: 0x32,
+    sh_flags: 0x08,
+    sh_addr: 0x0c,
+    sh_offset: 0x10,
+    sh_size: 0x14,
+    sh_link: 0x18,
+    sh_addralign: 0x20,
+    min_shentsize: 0x28,
+    p_flags: 0x18,
+    p_vaddr: 0x08,
+    p_memsz: 0x14,
+    min_phentsize: 0x20,
+};
+
+const LAYOUT_64: Layout = Layout {
+    e_phoff: 0x20,
+    e_shoff: 0x28,
+    e_phentsize: 0x36,
+    e_phnum: 0x38,
+    e_shentsize: 0x3a,
+    e_shnum: 0x3c,
+    e_shstrndx: 0x3e,
+    sh_flags: 0x08,
+    sh_addr: 0x10,
+    sh_offset: 0x18,
+    sh_size: 0x20,
+    sh_link: 0x28,
+    sh_addralign: 0x30,
+    min_shentsize: 0x40,
+    p_flags: 0x04,
+    p_vaddr: 0x10,
+    p_memsz: 0x28,
+    min_phentsize: 0x38,
+};
+
+struct Elf<'a> {
+    bytes: Bytes<'a>,
+    is_64: bool,
+    layout: &'static Layout,
+}
+
+impl Elf<'_> {
+    // Reads an address-sized field (u32 on ELF32, u64 on ELF64)
+    fn word(&self, offset: u64) -> io::Result<u64> {
+        if self.is_64 {
+            self.bytes.u64(offset)
+        } else {
+            self.bytes.u32(offset).map(u64::from)
+        }
+    }
+
+    // Finds the PT_LOAD program header whose memory range contains the given range
+    fn find_load_segment(&self, address: u64, size: u64) -> io::Result<Option<SegmentInfo>> {
+        let layout = self.layout;
+        let phoff = self.word(layout.e_phoff)?;
+        let phnum = self.bytes.u16(layout.e_phnum)?;
+        if phoff == 0 || phnum == 0 {
+            return Ok(None);
+        }
+        let phentsize = self.bytes.u16(layout.e_phentsize)?;
+        if phentsize < layout.min_phentsize {
+            return Err(malformed(format!(
+                "ELF program header size {phentsize} is too small"
+            )));
+        }
+
+        for index in 0..usize::from(phnum) {
+            let ph = table_entry(phoff, index as u64, u64::from(phentsize))?;
+            // Reading the type checks that `ph` is in bounds, so the fields can't overflow
+            if self.bytes.u32(ph)? != PT_LOAD {
+                continue;
+            }
+            let vaddr = self.word(ph + layout.p_vaddr)?;
+            let memsz = self.word(ph + layout.p_memsz)?;
+            if address >= vaddr && address.saturating_add(size) <= vaddr.saturating_add(memsz) {
+                let flags = self.bytes.u32(ph + layout.p_flags)?;
+                return Ok(Some(SegmentInfo {
+                    name: None,
+                    index,
+                    address: vaddr,
+                    size: memsz,
+                    is_writable: flags & PF_W != 0,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Lists the sections in the ELF section header table, with their names.
+pub(super) fn sections(data: &[u8]) -> io::Result<Vec<(String, SectionInfo)>> {
+    let ident = Bytes::new(data, false);
+    let is_64 = match ident.array::<1>(4)?[0] {
+        ELFCLASS32 => false,
+        ELFCLASS64 => true,
+        other => return Err(malformed(format!("unknown ELF class {other}"))),
+    };
+    let big_endian = match ident.array::<1>(5)?[0] {
+        ELFDATA2LSB => false,
+        ELFDATA2MSB => true,
+        other => return Err(malformed(format!("unknown ELF data encoding {other}"))),
+    };
+    let elf = Elf {
+        bytes: Bytes::new(data, big_endian),
+        is_64,
+        layout: if is_64 { &LAYOUT_64 } else { &LAYOUT_32 },
+    };
+    let layout = elf.layout;
+
+    let shoff = elf.word(layout.e_shoff)?;
+    if shoff == 0 {
+        // No section headers (e.g. stripped with `--strip-sections`)
+        return Ok(Vec::new());
+    }
+    let shentsize = elf.bytes.u16(layout.e_shentsize)?;
+    if shentsize < layout.min_shentsize {
+        return Err(malformed(format!(
+            "ELF section header size {shentsize} is too small"
+        )));
+    }
+    let header_offset = |idx: u64| table_entry(shoff, idx, u64::from(shentsize));
+
+    // If there are too many sections, the real counts are stored in section header 0
+    let mut shnum = u64::from(elf.bytes.u16(layout.e_shnum)?);
+    if shnum == 0 {
+        shnum = elf.word(checked_add(header_offset(0)?, layout.sh_size)?)?;
+    }
+    let mut shstrndx = u64::from(elf.bytes.u16(layout.e_shstrndx)?);
+    if shstrndx == u64::from(SHN_XINDEX) {
+        shstrndx = u64::from(
+            elf.bytes
+                .u32(checked_add(header_offset(0)?, layout.sh_link)?)?,
+        );
+    }
+    if shstrndx >= shnum {
+        return Err(malformed(format!(
+            "ELF section name table index {shstrndx} out of range ({shnum} sections)"
+        )));
+    }
+    let strtab_offset = elf.word(checked_add(header_offset(shstrndx)?, layout.sh_offset)?)?;
+
+    let mut sections = Vec::new();
+    // Section 0 is always the reserved null section
+    for idx in 1..shnum {
+        let sh = header_offset(idx)?;
+        // Reading the name checks that `sh` is in bounds, so the fields can't overflow
+        let name_offset = u64::from(elf.bytes.u32(sh)?);
+        let name = elf.bytes.c_str(checked_add(strtab_offset, name_offset)?)?;
+
+        let sh_type = elf.bytes.u32(sh + 4)?;
+        let flags = elf.word(sh + layout.sh_flags)?;
+        let address = elf.word(sh + layout.sh_addr)?;
+        let offset = elf.word(sh + layout.sh_offset)?;
+        let size = elf.word(sh + layout.sh_size)?;
+        let alignment = elf.word(sh + layout.sh_addralign)?;
+
+        // Only sections which are loaded at runtime can be in a segment
+        let segment = if flags & SHF_ALLOC != 0 {
+            elf.find_load_segment(address, size)?
+        } else {
+            None
+        };
+
+        sections.push((
+            String::from_utf8_lossy(name).into_owned(),
+            SectionInfo {
+                size: to_usize(size)?,
+                is_writable: flags & SHF_WRITE != 0,
+                file_offset: (sh_type != SHT_NOBITS).then_some(offset),
+                address,
+                alignment,
+                segment,
+            },
+        ));
+    }
+
+    Ok(sections)
+}
@@ -0,0 +1,177 @@
+//! Mach-O load command parsing.
+
+use std::io;
+
+use super::{Bytes, SectionInfo, SegmentInfo, malformed, to_usize};
+
+const MH_MAGIC: u32 = 0xfeed_face;
+const MH_MAGIC_64: u32 = 0xfeed_facf;
+const FAT_MAGIC: u32 = 0xcafe_babe;
+const FAT_MAGIC_64: u32 = 0xcafe_babf;
+
+const MH_OBJECT: u32 = 0x1;
+
+const LC_SEGMENT: u32 = 0x1;
+const LC_SEGMENT_64: u32 = 0x19;
+
+const VM_PROT_WRITE: u32 = 0x2;
+
+const SECTION_TYPE: u32 = 0xff;
+const S_ZEROFILL: u32 = 0x1;
+const S_GB_ZEROFILL: u32 = 0xc;
+const S_THREAD_LOCAL_ZEROFILL: u32 = 0x12;
+
+/// Returns true if the data starts with a (thin or universal) Mach-O magic number.
+pub(super) fn is_macho(data: &[u8]) -> bool {
+    let Some(magic) = data.get(..4) else {
+        return false;
+    };
+    let le = u32::from_le_bytes(magic.try_into().unwrap());
+    let be = u32::from_be_bytes(magic.try_into().unwrap());
+    [MH_MAGIC, MH_MAGIC_64].contains(&le)
+        || [MH_MAGIC, MH_MAGIC_64].contains(&be)
+        || [FAT_MAGIC, FAT_MAGIC_64].contains(&be)
+}
+
+/// Returns true if a section listed by [`sections`] has the given name.
+///
+/// `section_name` can be either just the section name (e.g., "ver_stub")
+/// or "segment,section" format (e.g., "__TEXT,ver_stub").
+pub(super) fn name_matches(listed_name: &str, section_name: &str) -> bool {
+    if section_name.contains(',') {
+        listed_name == section_name
+    } else {
+        listed_name.split_once(',').map(|(_, section)| section) == Some(section_name)
+    }
+}
+
+/// Lists the sections in the Mach-O segment load commands.
+///
+/// Sections are named in "segment,section" format (e.g., "__TEXT,ver_stub").
+/// Writability is determined by the initial protection of the containing segment.
+/// In object files, all sections are in a single unnamed segment, so instead the
+/// section's segment name is used: `__DATA` is writable, `__TEXT` is not.
+pub(super) fn sections(data: &[u8]) -> io::Result<Vec<(String, SectionInfo)>> {
+    let magic = Bytes::new(data, true).u32(0)?;
+    if magic == FAT_MAGIC || magic == FAT_MAGIC_64 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "universal (fat) Mach-O binaries are not supported, patch each architecture separately",
+        ));
+    }
+
+    let (bytes, is_64) = match Bytes::new(data, false).u32(0)? {
+        MH_MAGIC => (Bytes::new(data, false), false),
+        MH_MAGIC_64 => (Bytes::new(data, false), true),
+        _ => match magic {
+            MH_MAGIC => (Bytes::new(data, true), false),
+            MH_MAGIC_64 => (Bytes::new(data, true), true),
+            _ => return Err(malformed("bad Mach-O magic")),
+        },
+    };
+
+    let filetype = bytes.u32(0xc)?;
+    let ncmds = bytes.u32(0x10)?;
+    let mut cmd_offset: u64 = if is_64 { 32 } else { 28 };
+
+    let mut sections = Vec::new();
+    let mut segment_index = 0;
+    for _ in 0..ncmds {
+        let cmd = bytes.u32(cmd_offset)?;
+        let cmdsize = bytes.u32(cmd_offset + 4)?;
+        if cmdsize < 8 {
+            return Err(malformed(format!("Mach-O load command size {cmdsize}")));
+        }
+
+        if cmd == LC_SEGMENT || cmd == LC_SEGMENT_64 {
+            let segment_64 = cmd == LC_SEGMENT_64;
+            // Offsets of initprot / nsects, and header sizes, for segment_command(_64)
+            let (initprot_at, nsects_at, segment_size, section_size) = if segment_64 {
+                (60, 64, 72, 80)
+            } else {
+                (44, 48, 56, 68)
+            };
+            let initprot = bytes.u32(cmd_offset + initprot_at)?;
+            let nsects = bytes.u32(cmd_offset + nsects_at)?;
+
+            for i in 0..u64::from(nsects) {
+                let sect = cmd_offset + segment_size + i * section_size;
+                let sectname = bytes.fixed_str(sect, 16)?;
+                let segname = bytes.fixed_str(sect + 16, 16)?;
+
+                let (address, size, offset, align, flags) = if segment_64 {
+                    (
+                        bytes.u64(sect + 32)?,
+                        bytes.u64(sect + 40)?,
+                        bytes.u32(sect + 48)?,
+                        bytes.u32(sect + 52)?,
+                        bytes.u32(sect + 64)?,
+                    )
+                } else {
+                    (
+                        u64::from(bytes.u32(sect + 32)?),
+                        u64::from(bytes.u32(sect + 36)?),
+                        bytes.u32(sect + 40)?,
+                        bytes.u32(sect + 44)?,
+                        bytes.u32(sect + 56)?,
+                    )
+                };
+                let is_zerofill = matches!(
+                    flags & SECTION_TYPE,
+                    S_ZEROFILL | S_GB_ZEROFILL | S_THREAD_LOCAL_ZEROFILL
+                );
+                // Alignment is stored as a power of two
+                let alignment = 1u64
+                    .checked_shl(align)
+                    .ok_or_else(|| malformed(format!("Mach-O section alignment 2^{align}")))?;
+
+                let segment_writable = initprot & VM_PROT_WRITE != 0;
+                let is_writable = if filetype == MH_OBJECT {
+                    segname == b"__DATA"
+                } else {
+                    segment_writable
+                };
+
+                let (vmaddr, vmsize) = if segment_64 {
+                    (bytes.u64(cmd_offset + 24)?, bytes.u64(cmd_offset + 32)?)
+                } else {
+                    (
+                        u64::from(bytes.u32(cmd_offset + 24)?),
+                        u64::from(bytes.u32(cmd_offset + 28)?),
+                    )
+                };
+                let segment_name = bytes.fixed_str(cmd_offset + 8, 16)?;
+                let segment = SegmentInfo {
+                    name: (!segment_name.is_empty())
+                        .then(|| String::from_utf8_lossy(segment_name).into_owned()),
+                    index: segment_index,
+                    address: vmaddr,
+                    size: vmsize,
+                    is_writable: segment_writable,
+                };
+
+                sections.push((
+                    format!(
+                        "{},{}",
+                        String::from_utf8_lossy(segname),
+                        String::from_utf8_lossy(sectname)
+                    ),
+                    SectionInfo {
+                        size: to_usize(size)?,
+                        is_writable,
+                        file_offset: (!is_zerofill).then_some(u64::from(offset)),
+                        address,
+                        alignment,
+                        segment: Some(segment),
+                    },
+                ));
+            }
+
+            segment_index += 1;
+        }
+
+        cmd_offset += u64::from(cmdsize);
+    }
+
+    Ok(sections)
+}
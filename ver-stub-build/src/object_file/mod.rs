@@ -0,0 +1,452 @@
+//! Native parsing of object file headers, to locate sections without llvm-tools.
+//!
+//! This only reads the headers needed to find a section: ELF section and program
+//! headers, Mach-O segment load commands, and the PE section table.
+
+mod elf;
+mod macho;
+mod pe;
+
+use std::io;
+use std::path::Path;
+
+/// Binary format of an object file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFormat {
+    /// ELF
+    Elf,
+    /// Mach-O
+    MachO,
+    /// PE/COFF
+    Coff,
+}
+
+/// Information about a section in a binary.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SectionInfo {
+    /// Size of the section in bytes.
+    pub size: usize,
+    /// Whether the section is writable (has SHF_WRITE on ELF, or is in a writable segment on Mach-O).
+    pub is_writable: bool,
+    /// Offset of the section data in the file, or `None` if the section
+    /// occupies no space in the file (e.g. `SHT_NOBITS` or zerofill).
+    pub file_offset: Option<u64>,
+    /// Virtual address of the section (0 in relocatable object files).
+    pub address: u64,
+    /// Required alignment of the section in bytes.
+    pub alignment: u64,
+    /// The segment which contains the section, if any.
+    ///
+    /// This is the `PT_LOAD` program header on ELF, and the segment load command on Mach-O.
+    /// PE sections are not grouped into segments, so this is always `None` there.
+    pub segment: Option<SegmentInfo>,
+}
+
+/// Information about the segment containing a section.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SegmentInfo {
+    /// Name of the segment (e.g. `__TEXT`), on Mach-O only.
+    pub name: Option<String>,
+    /// Index of the segment among the program headers (ELF) or segment load commands (Mach-O).
+    pub index: usize,
+    /// Virtual address of the segment.
+    pub address: u64,
+    /// Size of the segment in memory.
+    pub size: u64,
+    /// Whether the segment is mapped writable.
+    pub is_writable: bool,
+}
+
+/// Gets information about a section in a binary, by parsing its headers directly.
+///
+/// This reads ELF section headers, Mach-O load commands, or the PE section table,
+/// and doesn't need `llvm-tools`.
+///
+/// Allows that the section name of interest may depend on the format of the binary.
+///
+/// Returns:
+/// Ok((binary_format, section_name, section_info_if_found))
+/// Returns `Ok(Some(SectionInfo))` if the section exists, `Ok(None)` if it doesn't,
+/// or `Err` if the file could not be read, or is not a recognized object file.
+pub fn get_section_info(
+    bin: impl AsRef<Path>,
+    section_name_fn: impl FnOnce(BinaryFormat) -> io::Result<String>,
+) -> io::Result<(BinaryFormat, String, Option<SectionInfo>)> {
+    let data = std::fs::read(bin)?;
+    find_section(&data, section_name_fn)
+}
+
+/// Detects the format of an object file and finds a section in it.
+///
+/// Same as [`get_section_info`], but for a file which is already in memory.
+pub(crate) fn find_section(
+    data: &[u8],
+    section_name_fn: impl FnOnce(BinaryFormat) -> io::Result<String>,
+) -> io::Result<(BinaryFormat, String, Option<SectionInfo>)> {
+    let (binary_format, sections) = list_sections(data)?;
+    let section_name = section_name_fn(binary_format)?;
+    let section_info = sections
+        .into_iter()
+        .find(|(name, _)| match binary_format {
+            BinaryFormat::MachO => macho::name_matches(name, &section_name),
+            _ => *name == section_name,
+        })
+        .map(|(_, info)| info);
+    Ok((binary_format, section_name, section_info))
+}
+
+/// Detects the format of an object file, and lists all of its sections in header order.
+///
+/// Section names are as they appear in the headers, except on Mach-O, where they
+/// are in "segment,section" format (e.g. `__TEXT,ver_stub`).
+///
+/// This takes the file contents rather than a path, so that callers which also
+/// need the section data only read the file once.
+pub fn list_sections(data: &[u8]) -> io::Result<(BinaryFormat, Vec<(String, SectionInfo)>)> {
+    let binary_format = BinaryFormat::detect_from_bytes(data).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "could not detect binary format (expected ELF, Mach-O or PE)",
+        )
+    })?;
+    let sections = match binary_format {
+        BinaryFormat::Elf => elf::sections(data),
+        BinaryFormat::MachO => macho::sections(data),
+        BinaryFormat::Coff => pe::sections(data),
+    }?;
+    Ok((binary_format, sections))
+}
+
+impl BinaryFormat {
+    /// Detect binary format from the magic bytes at the start of the file.
+    pub(crate) fn detect_from_bytes(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"\x7fELF") {
+            Some(Self::Elf)
+        } else if macho::is_macho(data) {
+            Some(Self::MachO)
+        } else if data.starts_with(b"MZ") {
+            Some(Self::Coff)
+        } else {
+            None
+        }
+    }
+}
+
+/// Bounds-checked, endian-aware reads from a byte slice.
+#[derive(Clone, Copy)]
+struct Bytes<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Bytes<'a> {
+    fn new(data: &'a [u8], big_endian: bool) -> Self {
+        Self { data, big_endian }
+    }
+
+    fn slice(&self, offset: u64, len: u64) -> io::Result<&'a [u8]> {
+        usize::try_from(offset)
+            .ok()
+            .zip(usize::try_from(len).ok())
+            .and_then(|(offset, len)| self.data.get(offset..offset.checked_add(len)?))
+            .ok_or_else(|| {
+                malformed(format!(
+                    "read of {len} bytes at offset {offset} is out of bounds (file size {})",
+                    self.data.len()
+                ))
+            })
+    }
+
+    fn array<const N: usize>(&self, offset: u64) -> io::Result<[u8; N]> {
+        Ok(self.slice(offset, N as u64)?.try_into().unwrap())
+    }
+
+    fn u16(&self, offset: u64) -> io::Result<u16> {
+        let b = self.array(offset)?;
+        Ok(if self.big_endian {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        })
+    }
+
+    fn u32(&self, offset: u64) -> io::Result<u32> {
+        let b = self.array(offset)?;
+        Ok(if self.big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        })
+    }
+
+    fn u64(&self, offset: u64) -> io::Result<u64> {
+        let b = self.array(offset)?;
+        Ok(if self.big_endian {
+            u64::from_be_bytes(b)
+        } else {
+            u64::from_le_bytes(b)
+        })
+    }
+
+    /// Reads a NUL-padded fixed-size name field (as in Mach-O and PE headers).
+    fn fixed_str(&self, offset: u64, len: u64) -> io::Result<&'a [u8]> {
+        let raw = self.slice(offset, len)?;
+        let end = raw.iter().position(|b| *b == 0).unwrap_or(raw.len());
+        Ok(&raw[..end])
+    }
+
+    /// Reads a NUL-terminated string starting at the given offset.
+    fn c_str(&self, offset: u64) -> io::Result<&'a [u8]> {
+        let rest = self.slice(offset, (self.data.len() as u64).saturating_sub(offset))?;
+        let end = rest
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| malformed(format!("unterminated string at offset {offset}")))?;
+        Ok(&rest[..end])
+    }
+}
+
+/// Converts a section size from the headers to `usize`.
+fn to_usize(size: u64) -> io::Result<usize> {
+    usize::try_from(size).map_err(|_| malformed(format!("section size {size} is too large")))
+}
+
+/// Adds an offset or address from the headers, failing instead of overflowing.
+fn checked_add(base: u64, offset: u64) -> io::Result<u64> {
+    base.checked_add(offset)
+        .ok_or_else(|| malformed(format!("offset {base} + {offset} overflows")))
+}
+
+/// Computes the offset of entry `idx` of a table in the file, failing instead of overflowing.
+fn table_entry(table: u64, idx: u64, entry_size: u64) -> io::Result<u64> {
+    idx.checked_mul(entry_size)
+        .and_then(|offset| table.checked_add(offset))
+        .ok_or_else(|| {
+            malformed(format!(
+                "entry {idx} of the table at offset {table} is out of bounds"
+            ))
+        })
+}
+
+fn malformed(msg: impl Into<String>) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("malformed object file: {}", msg.into()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds a minimal little-endian ELF64 file with a 16-byte `ver_stub` section.
+    fn minimal_elf64(ver_stub_flags: u64) -> Vec<u8> {
+        let shstrtab = b"\0ver_stub\0.shstrtab\0";
+        let mut data = vec![0u8; 104];
+        data[..7].copy_from_slice(b"\x7fELF\x02\x01\x01");
+        data[0x28..0x30].copy_from_slice(&104u64.to_le_bytes());
+        data[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+        data[0x3c..0x3e].copy_from_slice(&3u16.to_le_bytes());
+        data[0x3e..0x40].copy_from_slice(&2u16.to_le_bytes());
+        data[80..100].copy_from_slice(shstrtab);
+
+        let mut section_header = |name: u32, sh_type: u32, flags: u64, offset: u64, size: u64| {
+            let mut sh = [0u8; 64];
+            sh[0..4].copy_from_slice(&name.to_le_bytes());
+            sh[4..8].copy_from_slice(&sh_type.to_le_bytes());
+            sh[8..16].copy_from_slice(&flags.to_le_bytes());
+            if sh_type == 1 {
+                sh[0x10..0x18].copy_from_slice(&0x1000u64.to_le_bytes());
+                sh[0x30..0x38].copy_from_slice(&8u64.to_le_bytes());
+            }
+            sh[0x18..0x20].copy_from_slice(&offset.to_le_bytes());
+            sh[0x20..0x28].copy_from_slice(&size.to_le_bytes());
+            data.extend_from_slice(&sh);
+        };
+        section_header(0, 0, 0, 0, 0);
+        section_header(1, 1, ver_stub_flags, 64, 16);
+        section_header(10, 3, 0, 80, shstrtab.len() as u64);
+        data
+    }
+
+    // Builds a minimal little-endian 64-bit Mach-O executable with a 16-byte
+    // `__TEXT,ver_stub` section at file offset 256.
+    fn minimal_macho64() -> Vec<u8> {
+        let mut data = vec![0u8; 272];
+        let mut put = |offset: usize, bytes: &[u8]| {
+            data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        };
+        put(0, &0xfeed_facfu32.to_le_bytes());
+        put(0xc, &2u32.to_le_bytes());
+        put(0x10, &1u32.to_le_bytes());
+
+        // LC_SEGMENT_64, with one section_64 after it
+        put(32, &0x19u32.to_le_bytes());
+        put(36, &152u32.to_le_bytes());
+        put(40, b"__TEXT");
+        put(56, &0x1_0000_0000u64.to_le_bytes());
+        put(64, &0x2000u64.to_le_bytes());
+        put(92, &5u32.to_le_bytes());
+        put(96, &1u32.to_le_bytes());
+        put(104, b"ver_stub");
+        put(120, b"__TEXT");
+        put(136, &0x1_0000_1000u64.to_le_bytes());
+        put(144, &16u64.to_le_bytes());
+        put(152, &256u32.to_le_bytes());
+        put(156, &3u32.to_le_bytes());
+        data
+    }
+
+    // Builds a minimal PE32+ image with a 16-byte `ver_stub` section at file offset 0x200.
+    fn minimal_pe32_plus() -> Vec<u8> {
+        let mut data = vec![0u8; 0x400];
+        let mut put = |offset: usize, bytes: &[u8]| {
+            data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        };
+        put(0, b"MZ");
+        put(0x3c, &0x40u32.to_le_bytes());
+        put(0x40, b"PE\0\0");
+        put(0x46, &1u16.to_le_bytes());
+        put(0x54, &0xf0u16.to_le_bytes());
+
+        // Optional header, then the section table after it
+        put(0x58, &0x20bu16.to_le_bytes());
+        put(0x70, &0x1_4000_0000u64.to_le_bytes());
+        put(0x78, &0x1000u32.to_le_bytes());
+        put(0x148, b"ver_stub");
+        put(0x150, &16u32.to_le_bytes());
+        put(0x154, &0x2000u32.to_le_bytes());
+        put(0x158, &0x200u32.to_le_bytes());
+        put(0x15c, &0x200u32.to_le_bytes());
+        put(0x16c, &0x4000_0040u32.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_find_elf_section() {
+        let data = minimal_elf64(0x2);
+        let (format, name, location) = find_section(&data, |_| Ok("ver_stub".into())).unwrap();
+        assert_eq!(format, BinaryFormat::Elf);
+        assert_eq!(name, "ver_stub");
+        let info = location.unwrap();
+        assert_eq!(info.file_offset, Some(64));
+        assert_eq!(info.size, 16);
+        assert_eq!(info.address, 0x1000);
+        assert_eq!(info.alignment, 8);
+        assert!(!info.is_writable);
+        assert!(info.segment.is_none());
+
+        let data = minimal_elf64(0x3);
+        let (_, _, location) = find_section(&data, |_| Ok("ver_stub".into())).unwrap();
+        assert!(location.unwrap().is_writable);
+
+        let (_, _, location) = find_section(&data, |_| Ok("missing".into())).unwrap();
+        assert!(location.is_none());
+
+        let (_, sections) = list_sections(&data).unwrap();
+        let names: Vec<_> = sections.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["ver_stub", ".shstrtab"]);
+
+        assert!(find_section(&data[..200], |_| Ok("ver_stub".into())).is_err());
+        assert!(find_section(b"not an object file", |_| Ok("ver_stub".into())).is_err());
+    }
+
+    #[test]
+    fn test_offsets_overflow() {
+        // Section header table offset near the end of the address space
+        let mut data = minimal_elf64(0x2);
+        data[0x28..0x30].copy_from_slice(&(u64::MAX - 10).to_le_bytes());
+        assert!(list_sections(&data).is_err());
+
+        // Section name table offset which overflows when the name offset is added
+        let mut data = minimal_elf64(0x2);
+        let shstrtab_header = 104 + 2 * 64;
+        data[shstrtab_header + 0x18..shstrtab_header + 0x20]
+            .copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(list_sections(&data).is_err());
+    }
+
+    #[test]
+    fn test_find_macho_section() {
+        let data = minimal_macho64();
+        let (format, name, location) =
+            find_section(&data, |_| Ok("__TEXT,ver_stub".into())).unwrap();
+        assert_eq!(format, BinaryFormat::MachO);
+        assert_eq!(name, "__TEXT,ver_stub");
+        let info = location.unwrap();
+        assert_eq!(info.file_offset, Some(256));
+        assert_eq!(info.size, 16);
+        assert_eq!(info.address, 0x1_0000_1000);
+        assert_eq!(info.alignment, 8);
+        assert!(!info.is_writable);
+        let segment = info.segment.unwrap();
+        assert_eq!(segment.name.as_deref(), Some("__TEXT"));
+        assert_eq!((segment.index, segment.address), (0, 0x1_0000_0000));
+
+        // The section name alone matches too, in any segment
+        let (_, _, location) = find_section(&data, |_| Ok("ver_stub".into())).unwrap();
+        assert!(location.is_some());
+        let (_, _, location) = find_section(&data, |_| Ok("__DATA,ver_stub".into())).unwrap();
+        assert!(location.is_none());
+
+        assert!(list_sections(&data[..150]).is_err());
+    }
+
+    #[test]
+    fn test_macho_header_fields_out_of_range() {
+        // More load commands than the file holds
+        let mut data = minimal_macho64();
+        data[0x10..0x14].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(list_sections(&data).is_err());
+
+        // More sections than the file holds
+        let mut data = minimal_macho64();
+        data[96..100].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(list_sections(&data).is_err());
+
+        // Alignment of 2^64
+        let mut data = minimal_macho64();
+        data[156..160].copy_from_slice(&64u32.to_le_bytes());
+        assert!(list_sections(&data).is_err());
+    }
+
+    #[test]
+    fn test_find_pe_section() {
+        let data = minimal_pe32_plus();
+        let (format, name, location) = find_section(&data, |_| Ok("ver_stub".into())).unwrap();
+        assert_eq!(format, BinaryFormat::Coff);
+        assert_eq!(name, "ver_stub");
+        let info = location.unwrap();
+        assert_eq!(info.file_offset, Some(0x200));
+        assert_eq!(info.size, 16);
+        assert_eq!(info.address, 0x1_4000_2000);
+        assert_eq!(info.alignment, 0x1000);
+        assert!(!info.is_writable);
+        assert!(info.segment.is_none());
+
+        let (_, _, location) = find_section(&data, |_| Ok("missing".into())).unwrap();
+        assert!(location.is_none());
+
+        assert!(list_sections(&data[..0x160]).is_err());
+    }
+
+    #[test]
+    fn test_pe_header_fields_out_of_range() {
+        // PE header offset past the end of the file
+        let mut data = minimal_pe32_plus();
+        data[0x3c..0x40].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(list_sections(&data).is_err());
+
+        // More sections than the file holds
+        let mut data = minimal_pe32_plus();
+        data[0x46..0x48].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(list_sections(&data).is_err());
+
+        // Image base which overflows when the section's address is added
+        let mut data = minimal_pe32_plus();
+        data[0x70..0x78].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(list_sections(&data).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+//! PE/COFF section table parsing.
+
+use std::io;
+
+use super::{Bytes, SectionInfo, checked_add, malformed, to_usize};
+
+const IMAGE_SCN_MEM_WRITE: u32 = 0x8000_0000;
+
+const PE32_MAGIC: u16 = 0x10b;
+const PE32_PLUS_MAGIC: u16 = 0x20b;
+
+/// Lists the sections in the PE section table, with their names.
+///
+/// Note: section names in PE images are limited to 8 characters. We use `ver_stub`
+/// (8 chars) to fit within this limit.
+pub(super) fn sections(data: &[u8]) -> io::Result<Vec<(String, SectionInfo)>> {
+    let bytes = Bytes::new(data, false);
+
+    let pe_offset = u64::from(bytes.u32(0x3c)?);
+    if bytes.slice(pe_offset, 4)? != b"PE\0\0" {
+        return Err(malformed("missing PE signature"));
+    }
+    let coff = pe_offset + 4;
+    let num_sections = bytes.u16(coff + 2)?;
+    let optional_header_size = bytes.u16(coff + 16)?;
+    let optional_header = coff + 20;
+    let section_table = optional_header + u64::from(optional_header_size);
+
+    // Virtual addresses in the section table are relative to the image base
+    let image_base = match bytes.u16(optional_header)? {
+        PE32_MAGIC => u64::from(bytes.u32(optional_header + 28)?),
+        PE32_PLUS_MAGIC => bytes.u64(optional_header + 24)?,
+        other => {
+            return Err(malformed(format!(
+                "unknown PE optional header magic {other:#x}"
+            )));
+        }
+    };
+    let section_alignment = u64::from(bytes.u32(optional_header + 32)?);
+
+    let mut sections = Vec::new();
+    for i in 0..u64::from(num_sections) {
+        let sect = section_table + i * 40;
+        let name = bytes.fixed_str(sect, 8)?;
+
+        let virtual_size = bytes.u32(sect + 8)?;
+        let virtual_address = bytes.u32(sect + 12)?;
+        let raw_size = bytes.u32(sect + 16)?;
+        let raw_offset = bytes.u32(sect + 20)?;
+        let characteristics = bytes.u32(sect + 36)?;
+
+        // The raw data is padded to the file alignment, the virtual size is the
+        // actual size of the section contents.
+        let size = if virtual_size != 0 {
+            virtual_size.min(raw_size)
+        } else {
+            raw_size
+        };
+
+        sections.push((
+            String::from_utf8_lossy(name).into_owned(),
+            SectionInfo {
+                size: to_usize(u64::from(size))?,
+                is_writable: characteristics & IMAGE_SCN_MEM_WRITE != 0,
+                file_offset: (raw_size != 0).then_some(u64::from(raw_offset)),
+                address: checked_add(image_base, u64::from(virtual_address))?,
+                alignment: section_alignment,
+                segment: None,
+            },
+        ));
+    }
+
+    Ok(sections)
+}
@@ -0,0 +1,312 @@
+//! Fitting member data into the section when it doesn't fit.
+
+use crate::{Member, SectionFormat};
+
+/// What to do when the selected data doesn't fit in the section.
+///
+/// Set with [`LinkSection::with_overflow_policy`](crate::LinkSection::with_overflow_policy).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OverflowPolicy {
+    /// Fail with [`Error::SectionTooLarge`](crate::Error::SectionTooLarge).
+    ///
+    /// This is the default.
+    #[default]
+    Error,
+    /// Shorten free-text members, lowest priority first, until the data fits.
+    ///
+    /// Only the commit message and branch name are shortened, at UTF-8 character
+    /// boundaries, so they remain valid strings. A member which would be cut to nothing is
+    /// left out. Other members (the SHA, git describe, dates and timestamps, fields, and
+    /// the custom string) are left out whole, since a prefix of them isn't a meaningful value.
+    Truncate,
+    /// Leave out whole members, lowest priority first, until the data fits.
+    Drop,
+}
+
+/// The default priority of members, most important first.
+///
+/// When data has to be cut, the commit message goes first, since it's the longest and
+/// the least useful for identifying a build. The git SHA goes last.
+pub const DEFAULT_PRIORITY: [Member; Member::COUNT] = [
+    Member::GitSha,
+    Member::GitDescribe,
+    Member::GitCommitDate,
+    Member::BuildDate,
+    Member::GitCommitTimestamp,
+    Member::BuildTimestamp,
+    Member::GitBranch,
+    Member::Custom,
+    Member::Fields,
+    Member::GitCommitMsg,
+];
+
+/// A change made to a member to fit the data in the section.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Cut {
+    /// The member was shortened from `from` to `to` bytes.
+    Truncated {
+        member: Member,
+        from: usize,
+        to: usize,
+    },
+    /// The member was left out, it was `len` bytes.
+    Dropped { member: Member, len: usize },
+}
+
+impl Cut {
+    /// Describes the change, for a cargo warning.
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Self::Truncated { member, from, to } => {
+                format!("truncated {} from {from} to {to} bytes", member.name())
+            }
+            Self::Dropped { member, len } => {
+                format!("dropped {} ({len} bytes)", member.name())
+            }
+        }
+    }
+}
+
+/// Returns whether a member is free text, which can be shortened and still be useful.
+///
+/// Git describe isn't: cutting it could lose the `-dirty` suffix, or split the
+/// `-<n>-g<sha>` tail, which would then parse as a different version.
+fn is_free_text(member: Member) -> bool {
+    matches!(member, Member::GitCommitMsg | Member::GitBranch)
+}
+
+/// Returns the order in which members are cut, least important first.
+///
+/// Members in `priority` are most important first. Members which are not listed are
+/// less important than all listed members, and are ordered by [`DEFAULT_PRIORITY`].
+pub(crate) fn cut_order(priority: &[Member]) -> Vec<Member> {
+    let mut order = Vec::with_capacity(Member::COUNT);
+    for member in priority.iter().copied().chain(DEFAULT_PRIORITY) {
+        if !order.contains(&member) {
+            order.push(member);
+        }
+    }
+    order.reverse();
+    order
+}
+
+/// Cuts members according to the policy until the data fits in `buffer_size` bytes,
+/// and within the offsets of the format, and returns what was cut.
+///
+/// With [`OverflowPolicy::Error`], or if the data already fits, nothing is changed.
+/// If the header alone doesn't fit, everything may be cut and the data still won't fit;
+/// building the buffer then fails as usual.
+pub(crate) fn fit(
+    member_data: &mut [Option<String>; Member::COUNT],
+    buffer_size: usize,
+    format: SectionFormat,
+    policy: OverflowPolicy,
+    priority: &[Member],
+) -> Vec<Cut> {
+    let mut cuts = Vec::new();
+    if policy == OverflowPolicy::Error {
+        return cuts;
+    }
+
+    let header_size = format.header_size(Member::COUNT);
+    let capacity = buffer_size.min(header_size.saturating_add(format.max_offset()));
+    let used = |data: &[Option<String>; Member::COUNT]| {
+        header_size + data.iter().flatten().map(String::len).sum::<usize>()
+    };
+    for member in cut_order(priority) {
+        let excess = used(member_data).saturating_sub(capacity);
+        if excess == 0 {
+            break;
+        }
+        let Some(value) = &mut member_data[member as usize] else {
+            continue;
+        };
+
+        let len = value.len();
+        let mut new_len = match policy {
+            OverflowPolicy::Truncate if is_free_text(member) => len.saturating_sub(excess),
+            _ => 0,
+        };
+        while !value.is_char_boundary(new_len) {
+            new_len -= 1;
+        }
+
+        if new_len == 0 {
+            member_data[member as usize] = None;
+            cuts.push(Cut::Dropped { member, len });
+        } else {
+            value.truncate(new_len);
+            cuts.push(Cut::Truncated {
+                member,
+                from: len,
+                to: new_len,
+            });
+        }
+    }
+    cuts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(values: &[(Member, &str)]) -> [Option<String>; Member::COUNT] {
+        let mut data: [Option<String>; Member::COUNT] = Default::default();
+        for (member, value) in values {
+            data[*member as usize] = Some(value.to_string());
+        }
+        data
+    }
+
+    #[test]
+    fn test_cut_order() {
+        let order = cut_order(&[Member::GitCommitMsg, Member::GitSha]);
+        assert_eq!(order.len(), Member::COUNT);
+        assert_eq!(order[0], Member::Fields);
+        assert_eq!(
+            &order[Member::COUNT - 2..],
+            [Member::GitSha, Member::GitCommitMsg]
+        );
+    }
+
+    #[test]
+    fn test_fit_truncate() {
+        let header = SectionFormat::V2.header_size(Member::COUNT);
+        let mut member_data = data(&[
+            (Member::GitSha, "0123456789"),
+            (Member::GitCommitMsg, "héllo wörld"),
+        ]);
+
+        // "héllo wörld" is 13 bytes, cutting 4 would split the 'ö'
+        let cuts = fit(
+            &mut member_data,
+            header + 19,
+            SectionFormat::V2,
+            OverflowPolicy::Truncate,
+            &[],
+        );
+        assert_eq!(
+            cuts,
+            [Cut::Truncated {
+                member: Member::GitCommitMsg,
+                from: 13,
+                to: 8
+            }]
+        );
+        assert_eq!(
+            member_data[Member::GitCommitMsg as usize].as_deref(),
+            Some("héllo w")
+        );
+        assert_eq!(
+            member_data[Member::GitSha as usize].as_deref(),
+            Some("0123456789")
+        );
+
+        // The SHA can't be truncated, so it's dropped after the commit message
+        let cuts = fit(
+            &mut member_data,
+            header + 8,
+            SectionFormat::V2,
+            OverflowPolicy::Truncate,
+            &[],
+        );
+        assert_eq!(
+            cuts,
+            [
+                Cut::Dropped {
+                    member: Member::GitCommitMsg,
+                    len: 8
+                },
+                Cut::Dropped {
+                    member: Member::GitSha,
+                    len: 10
+                }
+            ]
+        );
+        assert_eq!(member_data, data(&[]));
+    }
+
+    #[test]
+    fn test_fit_truncate_structured() {
+        let header = SectionFormat::V2.header_size(Member::COUNT);
+        let mut member_data = data(&[
+            (Member::BuildTimestamp, "2024-01-15T12:34:56Z"),
+            (Member::GitBranch, "main"),
+        ]);
+
+        // Only one byte too many, but a shortened timestamp wouldn't parse
+        let cuts = fit(
+            &mut member_data,
+            header + 23,
+            SectionFormat::V2,
+            OverflowPolicy::Truncate,
+            &[Member::GitBranch],
+        );
+        assert_eq!(
+            cuts,
+            [Cut::Dropped {
+                member: Member::BuildTimestamp,
+                len: 20
+            }]
+        );
+        assert_eq!(member_data, data(&[(Member::GitBranch, "main")]));
+
+        // Cutting "-dirty" off a describe would hide that the build was dirty
+        let mut member_data = data(&[
+            (Member::GitDescribe, "v1.2.3-4-gabcdef0-dirty"),
+            (Member::GitBranch, "main"),
+        ]);
+        let cuts = fit(
+            &mut member_data,
+            header + 25,
+            SectionFormat::V2,
+            OverflowPolicy::Truncate,
+            &[Member::GitBranch],
+        );
+        assert_eq!(
+            cuts,
+            [Cut::Dropped {
+                member: Member::GitDescribe,
+                len: 23
+            }]
+        );
+        assert_eq!(member_data, data(&[(Member::GitBranch, "main")]));
+    }
+
+    #[test]
+    fn test_fit_drop_and_error() {
+        let header = SectionFormat::V2.header_size(Member::COUNT);
+        let values = [(Member::GitSha, "0123456789"), (Member::GitBranch, "main")];
+
+        let mut member_data = data(&values);
+        let cuts = fit(
+            &mut member_data,
+            header + 12,
+            SectionFormat::V2,
+            OverflowPolicy::Drop,
+            &[Member::GitBranch],
+        );
+        assert_eq!(
+            cuts,
+            [Cut::Dropped {
+                member: Member::GitSha,
+                len: 10
+            }]
+        );
+        assert_eq!(member_data, data(&[(Member::GitBranch, "main")]));
+
+        let mut member_data = data(&values);
+        assert!(
+            fit(
+                &mut member_data,
+                header + 12,
+                SectionFormat::V2,
+                OverflowPolicy::Error,
+                &[]
+            )
+            .is_empty()
+        );
+        assert_eq!(member_data, data(&values));
+    }
+}
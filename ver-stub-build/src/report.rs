@@ -0,0 +1,215 @@
+//! Structured results of patching a binary.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{BinaryFormat, ImageFormat, Member, SectionReader};
+
+/// What a patch operation did to the binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PatchAction {
+    /// The section was written, and the patched binary was written to the output path.
+    Patched,
+    /// The output already held exactly the patched binary, so nothing was written.
+    UpToDate,
+    /// The section doesn't exist in the input binary. It was copied to the output path
+    /// without modification, or left as is when patching in place.
+    SectionNotFound,
+}
+
+impl PatchAction {
+    /// Returns a short lowercase name for the action, e.g. `up_to_date`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Patched => "patched",
+            Self::UpToDate => "up_to_date",
+            Self::SectionNotFound => "section_not_found",
+        }
+    }
+}
+
+/// The result of patching a binary with [`UpdateSectionCommand`](crate::UpdateSectionCommand).
+///
+/// In a dry run, this describes what would have been done.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PatchReport {
+    /// What was done to the binary.
+    pub action: PatchAction,
+    /// Format of the input binary, or of the ELF file used to locate the section in a
+    /// firmware image. `None` if the section was found in an image by its marker.
+    pub binary_format: Option<BinaryFormat>,
+    /// Format of the input, if it is a firmware image rather than an object file.
+    pub image_format: Option<ImageFormat>,
+    /// Name of the section, e.g. `ver_stub` or `__TEXT,ver_stub`.
+    pub section_name: String,
+    /// Size of the section in bytes, or 0 if it was not found.
+    pub section_size: usize,
+    /// Number of bytes of the section used by the header and member data.
+    pub bytes_used: usize,
+    /// Number of bytes of the section left free.
+    pub bytes_free: usize,
+    /// The value of every member in the section, in [`Member::ALL`] order.
+    pub members: Vec<(Member, Option<String>)>,
+    /// Path of the output binary.
+    pub output_path: PathBuf,
+}
+
+impl PatchReport {
+    /// Creates a report from the bytes written into the section, which are empty if
+    /// the section was not found.
+    pub(crate) fn new(
+        action: PatchAction,
+        binary_format: Option<BinaryFormat>,
+        section_name: String,
+        section_bytes: &[u8],
+        output_path: &Path,
+    ) -> Self {
+        let reader = SectionReader::new(section_bytes);
+        let bytes_used = if section_bytes.is_empty() {
+            0
+        } else {
+            // The bytes were built by this crate, so they are well formed
+            reader.used_len().unwrap_or(section_bytes.len())
+        };
+        let members = Member::ALL
+            .into_iter()
+            .map(|member| {
+                let value = reader.get(member).ok().flatten();
+                (member, value.map(str::to_string))
+            })
+            .collect();
+        Self {
+            action,
+            binary_format,
+            image_format: None,
+            section_name,
+            section_size: section_bytes.len(),
+            bytes_used,
+            bytes_free: section_bytes.len() - bytes_used,
+            members,
+            output_path: output_path.to_path_buf(),
+        }
+    }
+
+    /// Returns the value of a member, if it was written.
+    pub fn get(&self, member: Member) -> Option<&str> {
+        self.members
+            .iter()
+            .find(|(m, _)| *m == member)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// Renders the report as a JSON object, e.g. for archiving alongside a release.
+    ///
+    /// Members are keyed by [`Member::name`], with `null` for absent members.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\n");
+        writeln!(out, "  \"action\": {},", json_string(self.action.name())).unwrap();
+        match self.binary_format {
+            Some(format) => writeln!(
+                out,
+                "  \"binary_format\": {},",
+                json_string(&format!("{format:?}"))
+            ),
+            None => writeln!(out, "  \"binary_format\": null,"),
+        }
+        .unwrap();
+        match self.image_format {
+            Some(format) => writeln!(out, "  \"image_format\": {},", json_string(format.name())),
+            None => writeln!(out, "  \"image_format\": null,"),
+        }
+        .unwrap();
+        writeln!(
+            out,
+            "  \"section_name\": {},",
+            json_string(&self.section_name)
+        )
+        .unwrap();
+        writeln!(out, "  \"section_size\": {},", self.section_size).unwrap();
+        writeln!(out, "  \"bytes_used\": {},", self.bytes_used).unwrap();
+        writeln!(out, "  \"bytes_free\": {},", self.bytes_free).unwrap();
+        out.push_str("  \"members\": {");
+        for (i, (member, value)) in self.members.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write!(out, "\n    {}: ", json_string(member.name())).unwrap();
+            match value {
+                Some(value) => out.push_str(&json_string(value)),
+                None => out.push_str("null"),
+            }
+        }
+        out.push_str("\n  },\n");
+        writeln!(
+            out,
+            "  \"output_path\": {}",
+            json_string(&self.output_path.to_string_lossy())
+        )
+        .unwrap();
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Quotes and escapes a string as a JSON string literal.
+///
+/// This is what [`PatchReport::to_json`] uses, and `ver-stub show --format json` too.
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patch_report() {
+        let mut bytes = vec![0u8; 32];
+        bytes[..7].copy_from_slice(&[1u8, 4, 0, b'a', b's', b'd', b'f']);
+        let report = PatchReport::new(
+            PatchAction::Patched,
+            Some(BinaryFormat::Elf),
+            "ver_stub".into(),
+            &bytes,
+            Path::new("out/my-bin"),
+        );
+        assert_eq!(report.section_size, 32);
+        assert_eq!(report.bytes_used, 7);
+        assert_eq!(report.bytes_free, 25);
+        assert_eq!(report.get(Member::GitSha), Some("asdf"));
+        assert_eq!(report.get(Member::GitBranch), None);
+
+        let json = report.to_json();
+        assert!(json.contains("\"action\": \"patched\",\n"));
+        assert!(json.contains("\"git_sha\": \"asdf\",\n"));
+        assert!(json.contains("\"custom\": null,\n"));
+        assert!(json.contains("\"fields\": null\n"));
+        assert!(json.contains("\"image_format\": null,\n"));
+
+        let report = PatchReport::new(
+            PatchAction::SectionNotFound,
+            Some(BinaryFormat::Coff),
+            "ver_stub".into(),
+            &[],
+            Path::new("out/my-bin"),
+        );
+        assert_eq!((report.section_size, report.bytes_used), (0, 0));
+        assert!(report.members.iter().all(|(_, value)| value.is_none()));
+    }
+}
@@ -4,10 +4,14 @@ use std::env::consts::EXE_SUFFIX;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::BinaryFormat;
 use crate::Error;
 use crate::LinkSection;
 use crate::cargo_helpers::{self, cargo_rerun_if, cargo_warning};
-use crate::llvm_tools::{BinaryFormat, LlvmTools};
+use crate::image::{self, ImageFormat, ImageLocator};
+use crate::llvm_tools::LlvmTools;
+use crate::object_file;
+use crate::report::{PatchAction, PatchReport};
 
 /// The section name is platform specific, and needs to depend on the
 /// target platform. This function gets the correct name for each binary format.
@@ -21,18 +25,76 @@ pub fn platform_section_name(binary_format: BinaryFormat) -> std::io::Result<Str
     .into())
 }
 
+/// Selects how `UpdateSectionCommand` writes the section into the binary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PatchBackend {
+    /// Use `llvm-objcopy` from the `llvm-tools` rustup component.
+    ///
+    /// This is the default.
+    #[default]
+    Llvm,
+    /// Parse the ELF, Mach-O or PE headers natively to find the section's file offset,
+    /// and overwrite its bytes in place.
+    ///
+    /// This doesn't need `llvm-tools`, `rustc`, or any subprocess. Since the section
+    /// size never changes, the rest of the binary is left byte-for-byte identical.
+    /// Universal (fat) Mach-O binaries are not supported.
+    Native,
+}
+
+/// What an `UpdateSectionCommand` writes into the section.
+pub(crate) enum SectionContents {
+    /// Version data collected by a `LinkSection`, sized to fit the section.
+    Data(LinkSection),
+    /// All zeroes, which is what the section contains in a freshly linked binary.
+    Zero,
+}
+
 /// Builder for updating sections in a binary.
 ///
-/// Created by calling `LinkSection::patch_into()` or `LinkSection::patch_into_bin_dep()`.
+/// Created by calling `LinkSection::patch_into()`, `LinkSection::patch_into_bin_dep()`,
+/// or [`UpdateSectionCommand::zero_section()`].
 #[must_use]
 pub struct UpdateSectionCommand {
-    pub(crate) link_section: LinkSection,
+    pub(crate) contents: SectionContents,
     pub(crate) bin_path: PathBuf,
     pub(crate) new_name: Option<String>,
     pub(crate) dry_run: bool,
+    pub(crate) backend: PatchBackend,
+    pub(crate) image: Option<(ImageFormat, ImageLocator)>,
 }
 
 impl UpdateSectionCommand {
+    /// Creates a command which fills the section with zeroes, rather than version data.
+    ///
+    /// This turns a patched binary back into its canonical unpatched form, since an
+    /// all-zero section decodes as "all members absent". With [`PatchBackend::Native`],
+    /// the result is byte-identical to the binary as it was originally linked, provided
+    /// it was also patched natively. `llvm-objcopy` may rewrite other parts of the
+    /// binary, such as the symbol table.
+    ///
+    /// This is useful for checking that builds are reproducible, or for using the binary
+    /// as a cache key.
+    ///
+    /// ```no_run
+    /// use ver_stub_build::UpdateSectionCommand;
+    ///
+    /// UpdateSectionCommand::zero_section("target/release/my-bin.bin")
+    ///     .write_to("target/release/my-bin.unpatched")
+    ///     .unwrap();
+    /// ```
+    pub fn zero_section(binary_path: impl AsRef<Path>) -> Self {
+        Self {
+            contents: SectionContents::Zero,
+            bin_path: binary_path.as_ref().to_path_buf(),
+            new_name: None,
+            dry_run: false,
+            backend: PatchBackend::default(),
+            image: None,
+        }
+    }
+
     /// Sets a custom filename for the output binary.
     ///
     /// This can only be used when:
@@ -53,6 +115,42 @@ impl UpdateSectionCommand {
         self
     }
 
+    /// Selects the backend used to patch the binary (default: [`PatchBackend::Llvm`]).
+    pub fn with_backend(mut self, backend: PatchBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Treats the input as a firmware image in the given format, rather than an object file.
+    ///
+    /// The section is found in the image as described by `locator`, either at the address
+    /// it has in the ELF file the image was made from, or by scanning for the marker written
+    /// by a previous patch in format version 2. HEX and S-record checksums are updated to match the new data.
+    /// The backend is not used, images are always patched natively.
+    ///
+    /// When writing to a directory, the default output name is `{stem}.patched.{extension}`.
+    ///
+    /// ```no_run
+    /// use ver_stub_build::{ImageFormat, ImageLocator, LinkSection};
+    ///
+    /// LinkSection::new()
+    ///     .with_all_git()
+    ///     .patch_into("target/thumbv7em-none-eabihf/release/firmware.hex")
+    ///     .with_image(
+    ///         ImageFormat::IntelHex,
+    ///         ImageLocator::Elf {
+    ///             path: "target/thumbv7em-none-eabihf/release/firmware".into(),
+    ///             base_address: None,
+    ///         },
+    ///     )
+    ///     .write_to("dist")
+    ///     .unwrap();
+    /// ```
+    pub fn with_image(mut self, format: ImageFormat, locator: ImageLocator) -> Self {
+        self.image = Some((format, locator));
+        self
+    }
+
     /// Writes the patched binary to the specified path.
     ///
     /// If the path is a directory, the output filename will be determined by
@@ -63,7 +161,13 @@ impl UpdateSectionCommand {
     ///
     /// If the section doesn't exist in the input binary, a warning is logged and the
     /// binary is copied without modification.
-    pub fn write_to(self, path: impl AsRef<Path>) -> Result<(), Error> {
+    ///
+    /// If the output already exists, holds exactly the section contents about to be written,
+    /// and is not older than the input binary, it is left untouched (keeping its modification
+    /// time), and a message is logged that patching was skipped.
+    ///
+    /// Returns a [`PatchReport`] describing what was done.
+    pub fn write_to(self, path: impl AsRef<Path>) -> Result<PatchReport, Error> {
         eprintln!("ver-stub-build: input binary = {}", self.bin_path.display());
 
         // Emit rerun-if-changed for the input binary
@@ -85,7 +189,16 @@ impl UpdateSectionCommand {
             let base_name = original_name
                 .strip_suffix(EXE_SUFFIX)
                 .unwrap_or(original_name);
-            let default_name = format!("{}.bin{}", base_name, EXE_SUFFIX);
+            let default_name = match (&self.image, self.bin_path.extension()) {
+                // Images keep their extension, which tools use to detect the format
+                (Some(_), Some(extension)) => format!(
+                    "{}.patched.{}",
+                    Path::new(original_name).with_extension("").display(),
+                    extension.to_string_lossy()
+                ),
+                (Some(_), None) => format!("{}.patched", original_name),
+                (None, _) => format!("{}.bin{}", base_name, EXE_SUFFIX),
+            };
             let output_name = self.new_name.as_deref().unwrap_or(&default_name);
             path.join(output_name)
         } else {
@@ -100,6 +213,155 @@ impl UpdateSectionCommand {
             path.to_path_buf()
         };
 
+        let bin_path = self.bin_path.clone();
+        let dry_run = self.dry_run;
+        let report = self.patch_to(&output_path, &output_path)?;
+        match report.action {
+            PatchAction::Patched => {
+                if !dry_run {
+                    eprintln!(
+                        "ver-stub-build: wrote patched binary to {}",
+                        output_path.display()
+                    );
+                }
+            }
+            PatchAction::UpToDate => {
+                eprintln!(
+                    "ver-stub-build: {} is already up to date, skipped patching",
+                    output_path.display()
+                );
+            }
+            PatchAction::SectionNotFound => {
+                // Section doesn't exist, copy binary without modification
+                cargo_warning(&format!(
+                    "section '{}' not found in {}, copying without modification",
+                    report.section_name,
+                    bin_path.display()
+                ));
+                if !dry_run {
+                    fs::copy(&bin_path, &output_path).map_err(|source| Error::CopyBinary {
+                        from: bin_path.clone(),
+                        to: output_path.clone(),
+                        source,
+                    })?;
+                    eprintln!("ver-stub-build: copied to {}", output_path.display());
+                } else {
+                    eprintln!(
+                        "Command: {{ program: \"cp\", args: [\"cp\", {:?}, {:?}] }}",
+                        bin_path, output_path
+                    );
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Patches the input binary in place.
+    ///
+    /// The patched binary is first written to a temporary file in the same directory,
+    /// which is given the permissions and modification time of the original, and then
+    /// atomically renamed over it. If patching fails, the original binary is left untouched.
+    ///
+    /// Keeping the modification time means that cargo won't consider the binary to have
+    /// changed, which avoids spurious reruns of build scripts that watch it.
+    ///
+    /// If the section doesn't exist in the input binary, or already holds exactly the
+    /// contents about to be written, the binary is left unmodified.
+    ///
+    /// `with_filename()` has no effect in this mode.
+    ///
+    /// Returns a [`PatchReport`] describing what was done.
+    pub fn in_place(self) -> Result<PatchReport, Error> {
+        eprintln!("ver-stub-build: input binary = {}", self.bin_path.display());
+
+        // Emit rerun-if-changed for the input binary
+        // See: https://doc.rust-lang.org/cargo/reference/build-scripts.html#rerun-if-changed
+        cargo_rerun_if(&format!("changed={}", self.bin_path.display()));
+
+        let bin_path = self.bin_path.clone();
+        let dry_run = self.dry_run;
+        let replace_err = |source| Error::ReplaceBinary {
+            path: bin_path.clone(),
+            source,
+        };
+
+        let metadata = fs::metadata(&bin_path).map_err(replace_err)?;
+        let dir = match bin_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let file_name = bin_path
+            .file_name()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        // Removed on drop, unless it gets persisted
+        let temp_file = tempfile::Builder::new()
+            .prefix(&format!(".{file_name}."))
+            .tempfile_in(dir)
+            .map_err(replace_err)?;
+        let temp_path = temp_file.path().to_path_buf();
+
+        let report = self.patch_to(&temp_path, &bin_path)?;
+        match report.action {
+            PatchAction::Patched => {
+                if dry_run {
+                    eprintln!(
+                        "ver-stub-build: would replace {} with {}",
+                        bin_path.display(),
+                        temp_path.display()
+                    );
+                    return Ok(report);
+                }
+
+                // Set the mtime before the permissions, since the binary may be read-only
+                fs::File::options()
+                    .write(true)
+                    .open(&temp_path)
+                    .and_then(|f| f.set_modified(metadata.modified()?))
+                    .map_err(replace_err)?;
+                fs::set_permissions(&temp_path, metadata.permissions()).map_err(replace_err)?;
+                temp_file
+                    .persist(&bin_path)
+                    .map_err(|e| replace_err(e.error))?;
+                eprintln!("ver-stub-build: patched {} in place", bin_path.display());
+            }
+            PatchAction::UpToDate => {
+                eprintln!(
+                    "ver-stub-build: {} is already up to date, skipped patching",
+                    bin_path.display()
+                );
+            }
+            PatchAction::SectionNotFound => {
+                cargo_warning(&format!(
+                    "section '{}' not found in {}, leaving it unmodified",
+                    report.section_name,
+                    bin_path.display()
+                ));
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Writes the patched binary to the given file path, using the selected backend.
+    ///
+    /// `final_path` is where the patched binary ends up, which differs from `output_path`
+    /// when patching in place. If it already holds exactly the patched binary, or the
+    /// section doesn't exist, nothing is written.
+    ///
+    /// The report's output path is `final_path`.
+    fn patch_to(mut self, output_path: &Path, final_path: &Path) -> Result<PatchReport, Error> {
+        if let Some((format, locator)) = self.image.take() {
+            return self.write_image(format, locator, output_path, final_path);
+        }
+        match self.backend {
+            PatchBackend::Llvm => self.write_with_llvm(output_path, final_path),
+            PatchBackend::Native => self.write_with_native(output_path, final_path),
+        }
+    }
+
+    fn write_with_llvm(self, output_path: &Path, final_path: &Path) -> Result<PatchReport, Error> {
         let mut llvm = LlvmTools::new().map_err(|source| Error::LlvmToolsNotFound { source })?;
 
         if self.dry_run {
@@ -114,63 +376,238 @@ impl UpdateSectionCommand {
                 source,
             })?;
 
-        match section_info {
-            Some(info) => {
-                // Warn if section is writable (should be read-only for security)
-                if info.is_writable {
-                    cargo_warning(&format!(
-                        "section '{}' is writable ({binary_format:?}); this is a minor bug, it should be in a read-only segment",
-                        section_name
-                    ));
-                }
+        let Some(info) = section_info else {
+            return Ok(PatchReport::new(
+                PatchAction::SectionNotFound,
+                Some(binary_format),
+                section_name,
+                &[],
+                final_path,
+            ));
+        };
+
+        warn_if_writable(&section_name, binary_format, info.is_writable);
+
+        let section_bytes = self.contents.build(info.size)?;
+        if is_up_to_date(&self.bin_path, final_path, &section_bytes) {
+            return Ok(PatchReport::new(
+                PatchAction::UpToDate,
+                Some(binary_format),
+                section_name,
+                &section_bytes,
+                final_path,
+            ));
+        }
+
+        llvm.update_section_with_bytes(&self.bin_path, output_path, &section_name, &section_bytes)
+            .map_err(|source| Error::UpdateSection {
+                binary_path: self.bin_path.clone(),
+                source,
+            })?;
+
+        Ok(PatchReport::new(
+            PatchAction::Patched,
+            Some(binary_format),
+            section_name,
+            &section_bytes,
+            final_path,
+        ))
+    }
+
+    fn write_with_native(
+        self,
+        output_path: &Path,
+        final_path: &Path,
+    ) -> Result<PatchReport, Error> {
+        let get_section_info_err = |source| Error::GetSectionInfo {
+            binary_path: self.bin_path.clone(),
+            source,
+        };
+
+        let data = fs::read(&self.bin_path).map_err(get_section_info_err)?;
+        let (binary_format, section_name, section_info) =
+            object_file::find_section(&data, platform_section_name)
+                .map_err(get_section_info_err)?;
+        drop(data);
+
+        let Some(info) = section_info else {
+            return Ok(PatchReport::new(
+                PatchAction::SectionNotFound,
+                Some(binary_format),
+                section_name,
+                &[],
+                final_path,
+            ));
+        };
+
+        warn_if_writable(&section_name, binary_format, info.is_writable);
+
+        let update_section_err = |source| Error::UpdateSection {
+            binary_path: self.bin_path.clone(),
+            source,
+        };
+        let file_offset = info.file_offset.ok_or_else(|| {
+            update_section_err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("section '{section_name}' has no data in the file"),
+            ))
+        })?;
+
+        let bin_path = self.bin_path.clone();
+        let dry_run = self.dry_run;
+        let section_bytes = self.contents.build(info.size)?;
+        if is_up_to_date(&bin_path, final_path, &section_bytes) {
+            return Ok(PatchReport::new(
+                PatchAction::UpToDate,
+                Some(binary_format),
+                section_name,
+                &section_bytes,
+                final_path,
+            ));
+        }
+
+        if dry_run {
+            eprintln!(
+                "ver-stub-build: would copy {} to {} and write {} bytes at offset {:#x}",
+                bin_path.display(),
+                output_path.display(),
+                section_bytes.len(),
+                file_offset
+            );
+            return Ok(PatchReport::new(
+                PatchAction::Patched,
+                Some(binary_format),
+                section_name,
+                &section_bytes,
+                final_path,
+            ));
+        }
+
+        // Copy first, so that the output keeps the permissions of the input
+        if !is_same_file(&bin_path, output_path) {
+            fs::copy(&bin_path, output_path).map_err(|source| Error::CopyBinary {
+                from: bin_path.clone(),
+                to: output_path.to_path_buf(),
+                source,
+            })?;
+        }
+        write_at(output_path, file_offset, &section_bytes).map_err(|source| {
+            Error::UpdateSection {
+                binary_path: bin_path.clone(),
+                source,
+            }
+        })?;
+
+        Ok(PatchReport::new(
+            PatchAction::Patched,
+            Some(binary_format),
+            section_name,
+            &section_bytes,
+            final_path,
+        ))
+    }
 
-                // Build section data with the correct buffer size from the binary
-                let section_bytes = self
-                    .link_section
-                    .with_buffer_size(info.size)
-                    .build_section_bytes();
-
-                llvm.update_section_with_bytes(
-                    &self.bin_path,
-                    &output_path,
-                    &section_name,
-                    &section_bytes,
-                )
-                .map_err(|source| Error::UpdateSection {
-                    binary_path: self.bin_path.clone(),
+    /// Writes the patched image to the given file path.
+    fn write_image(
+        self,
+        format: ImageFormat,
+        locator: ImageLocator,
+        output_path: &Path,
+        final_path: &Path,
+    ) -> Result<PatchReport, Error> {
+        let bin_path = self.bin_path.clone();
+        let data = fs::read(&bin_path).map_err(|source| Error::GetSectionInfo {
+            binary_path: bin_path.clone(),
+            source,
+        })?;
+
+        let by_marker = matches!(locator, ImageLocator::Marker { .. });
+
+        // Find the format of the ELF file (if any), the section, and the base address of a raw image
+        let (binary_format, section_name, located) = match locator {
+            ImageLocator::Elf { path, base_address } => {
+                cargo_rerun_if(&format!("changed={}", path.display()));
+                let get_section_info_err = |source| Error::GetSectionInfo {
+                    binary_path: path.clone(),
                     source,
-                })?;
-                if !self.dry_run {
-                    eprintln!(
-                        "ver-stub-build: wrote patched binary to {}",
-                        output_path.display()
-                    );
+                };
+                let elf_data = fs::read(&path).map_err(get_section_info_err)?;
+                let (binary_format, section_name, section_info) =
+                    object_file::find_section(&elf_data, platform_section_name)
+                        .map_err(get_section_info_err)?;
+                if let Some(info) = &section_info {
+                    warn_if_writable(&section_name, binary_format, info.is_writable);
                 }
+                let base_address = match base_address {
+                    Some(base_address) => base_address,
+                    None if format == ImageFormat::Raw => {
+                        let (_, sections) =
+                            object_file::list_sections(&elf_data).map_err(get_section_info_err)?;
+                        image::elf_base_address(&sections).unwrap_or(0)
+                    }
+                    None => 0,
+                };
+                let located = section_info.map(|info| (info.address, info.size, base_address));
+                (Some(binary_format), section_name, located)
             }
-            None => {
-                // Section doesn't exist, copy binary without modification
-                cargo_warning(&format!(
-                    "section '{}' not found in {}, copying without modification",
-                    section_name,
-                    self.bin_path.display()
-                ));
-                if !self.dry_run {
-                    fs::copy(&self.bin_path, &output_path).map_err(|source| Error::CopyBinary {
-                        from: self.bin_path.clone(),
-                        to: output_path.clone(),
+            ImageLocator::Marker { size } => {
+                let segments =
+                    image::load(&data, format, 0).map_err(|source| Error::GetSectionInfo {
+                        binary_path: bin_path.clone(),
                         source,
                     })?;
-                    eprintln!("ver-stub-build: copied to {}", output_path.display());
-                } else {
-                    eprintln!(
-                        "Command: {{ program: \"cp\", args: [\"cp\", {:?}, {:?}] }}",
-                        self.bin_path, output_path
-                    );
-                }
+                let located = image::find_marker(&segments).map(|address| (address, size, 0));
+                // Images have no section headers, so use the ELF section name
+                (None, String::from("ver_stub"), located)
             }
+        };
+        let report = |action, section_bytes: &[u8]| {
+            let mut report = PatchReport::new(
+                action,
+                binary_format,
+                section_name.clone(),
+                section_bytes,
+                final_path,
+            );
+            report.image_format = Some(format);
+            report
+        };
+
+        let Some((address, size, base_address)) = located else {
+            return Ok(report(PatchAction::SectionNotFound, &[]));
+        };
+
+        // Keep the marker, so that the section can be found again next time
+        let contents = match self.contents {
+            SectionContents::Data(link_section) if by_marker => {
+                SectionContents::Data(link_section.with_marker_format(size)?)
+            }
+            contents => contents,
+        };
+        let section_bytes = contents.build(size)?;
+        let update_section_err = |source| Error::UpdateSection {
+            binary_path: bin_path.clone(),
+            source,
+        };
+        let patched = image::patch(&data, format, base_address, address, &section_bytes)
+            .map_err(update_section_err)?;
+        if is_image_up_to_date(&bin_path, final_path, &patched) {
+            return Ok(report(PatchAction::UpToDate, &section_bytes));
+        }
+
+        if self.dry_run {
+            eprintln!(
+                "ver-stub-build: would write {} to {} with {} bytes at address {:#x}",
+                bin_path.display(),
+                output_path.display(),
+                section_bytes.len(),
+                address
+            );
+        } else {
+            fs::write(output_path, patched).map_err(update_section_err)?;
         }
 
-        Ok(())
+        Ok(report(PatchAction::Patched, &section_bytes))
     }
 
     /// Writes the patched binary to the target profile directory (e.g., `target/debug/`).
@@ -183,8 +620,102 @@ impl UpdateSectionCommand {
     /// - <https://github.com/rust-lang/cargo/issues/9661#issuecomment-1769481293>
     /// - <https://github.com/rust-lang/cargo/issues/9661#issuecomment-2159267601>
     /// - <https://github.com/rust-lang/cargo/issues/13663>
-    pub fn write_to_target_profile_dir(self) -> Result<(), Error> {
+    pub fn write_to_target_profile_dir(self) -> Result<PatchReport, Error> {
         let target_dir = cargo_helpers::target_profile_dir();
         self.write_to(target_dir)
     }
 }
+
+impl SectionContents {
+    /// Builds the bytes to write into a section of the given size.
+    fn build(self, size: usize) -> Result<Vec<u8>, Error> {
+        match self {
+            // Build section data with the correct buffer size from the binary
+            Self::Data(link_section) => link_section
+                .with_buffer_size(size)
+                .try_build_section_bytes(),
+            Self::Zero => Ok(vec![0u8; size]),
+        }
+    }
+}
+
+/// Warn if section is writable (should be read-only for security)
+fn warn_if_writable(section_name: &str, binary_format: BinaryFormat, is_writable: bool) {
+    if is_writable {
+        cargo_warning(&format!(
+            "section '{}' is writable ({binary_format:?}); this is a minor bug, it should be in a read-only segment",
+            section_name
+        ));
+    }
+}
+
+/// Returns true if `output` already holds `input` with the given section contents,
+/// so that patching can be skipped.
+///
+/// This avoids rewriting large binaries, and bumping their modification time, when a build
+/// script reruns without anything having changed. Besides the section contents matching,
+/// the output must not be older than the input, since the input may have been rebuilt.
+///
+/// Only the section is compared, not the rest of the file: `llvm-objcopy` lays out its
+/// output differently from the input, so the two can't be compared byte for byte, and
+/// reading the input as well would double the cost of every build script run. So an
+/// output which was changed outside the section after the input was built isn't noticed.
+fn is_up_to_date(input: &Path, output: &Path, section_bytes: &[u8]) -> bool {
+    let Ok(data) = fs::read(output) else {
+        return false;
+    };
+    let Ok((_, _, Some(info))) = object_file::find_section(&data, platform_section_name) else {
+        return false;
+    };
+    let existing = info
+        .file_offset
+        .and_then(|offset| usize::try_from(offset).ok())
+        .and_then(|offset| data.get(offset..offset.checked_add(section_bytes.len())?));
+    existing == Some(section_bytes)
+        && info.size == section_bytes.len()
+        && is_not_older(input, output)
+}
+
+/// Same as [`is_up_to_date`], for a firmware image: `output` must hold exactly the
+/// patched image. Images are always patched natively, so unlike object files, the
+/// whole output can be compared.
+fn is_image_up_to_date(input: &Path, output: &Path, patched: &[u8]) -> bool {
+    fs::read(output).is_ok_and(|data| data == patched) && is_not_older(input, output)
+}
+
+/// Returns true if `output` is the same file as `input`, or was modified no earlier than it.
+fn is_not_older(input: &Path, output: &Path) -> bool {
+    if is_same_file(input, output) {
+        return true;
+    }
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified());
+    matches!((modified(input), modified(output)), (Ok(input), Ok(output)) if output >= input)
+}
+
+/// Returns true if both paths exist and refer to the same file.
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Overwrites bytes of an existing file at the given offset.
+fn write_at(path: &Path, offset: u64, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::{self, Seek, SeekFrom, Write};
+
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    let len = file.metadata()?.len();
+    if offset + bytes.len() as u64 > len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "section data at offset {offset:#x} ({} bytes) extends past end of file ({len} bytes)",
+                bytes.len()
+            ),
+        ));
+    }
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(bytes)?;
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! Reading a binary and locating its ver_stub section by parsing its headers.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use ver_stub_build::{SectionInfo, list_sections, platform_section_name};
+
+/// A binary read into memory, with its section headers parsed.
+pub struct Binary {
+    path: PathBuf,
+    data: Vec<u8>,
+    sections: Vec<(String, SectionInfo)>,
+    // Index of the ver_stub section in `sections`, and the byte range of its contents
+    ver_stub_index: usize,
+    ver_stub: (usize, usize),
+}
+
+impl Binary {
+    /// Reads a binary and locates its ver_stub section.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let data = std::fs::read(path)?;
+        let (binary_format, sections) = list_sections(&data)?;
+        let section_name = platform_section_name(binary_format)?;
+        let not_found = || {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("section '{section_name}' not found"),
+            )
+        };
+        let ver_stub_index = sections
+            .iter()
+            .position(|(name, _)| *name == section_name)
+            .ok_or_else(not_found)?;
+        let ver_stub = section_range(&data, &sections[ver_stub_index].1)?.ok_or_else(not_found)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            data,
+            sections,
+            ver_stub_index,
+            ver_stub,
+        })
+    }
+
+    /// Returns the path the binary was read from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the platform-specific name of the ver_stub section.
+    pub fn ver_stub_name(&self) -> &str {
+        &self.sections[self.ver_stub_index].0
+    }
+
+    /// Returns the contents of the ver_stub section.
+    pub fn ver_stub_bytes(&self) -> &[u8] {
+        &self.data[self.ver_stub.0..self.ver_stub.1]
+    }
+
+    /// Returns the file contents, with the ver_stub section filled with zeroes.
+    pub fn zeroed(&self) -> Vec<u8> {
+        let mut data = self.data.clone();
+        data[self.ver_stub.0..self.ver_stub.1].fill(0);
+        data
+    }
+
+    /// Returns the contents of every other section, in header order.
+    ///
+    /// Sections which occupy no space in the file (e.g. `.bss`) have no contents.
+    pub fn other_sections(&self) -> io::Result<Vec<Section<'_>>> {
+        self.sections
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != self.ver_stub_index)
+            .map(|(_, (name, info))| {
+                let range = section_range(&self.data, info)?;
+                Ok((name.as_str(), range.map(|(s, e)| &self.data[s..e])))
+            })
+            .collect()
+    }
+}
+
+/// The name and contents of a section.
+pub type Section<'a> = (&'a str, Option<&'a [u8]>);
+
+/// Returns the byte range of a section's contents in the file, if it has any.
+fn section_range(data: &[u8], info: &SectionInfo) -> io::Result<Option<(usize, usize)>> {
+    let Some(offset) = info.file_offset else {
+        return Ok(None);
+    };
+    usize::try_from(offset)
+        .ok()
+        .and_then(|start| Some((start, start.checked_add(info.size)?)))
+        .filter(|(_, end)| *end <= data.len())
+        .map(Some)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "section of {} bytes at offset {offset} is out of bounds (file size {})",
+                    info.size,
+                    data.len()
+                ),
+            )
+        })
+}
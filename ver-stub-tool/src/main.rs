@@ -1,6 +1,28 @@
 use conf::{Conf, Subcommands};
-use std::path::PathBuf;
-use ver_stub_build::{LinkSection, LlvmTools, platform_section_name};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use ver_stub_build::{
+    ImageFormat, ImageLocator, LinkSection, LlvmTools, Member, OverflowPolicy, PatchBackend,
+    SectionFormat, SectionReader, UpdateSectionCommand, platform_section_name,
+};
+
+/// Reading a binary and locating its ver_stub section by parsing its headers.
+mod binary;
+
+/// Decoding and printing the contents of a ver_stub section.
+mod show;
+
+/// Checking the contents of a ver_stub section against expected values.
+mod verify;
+
+/// Comparing two binaries while ignoring the contents of their ver_stub sections.
+mod repro_diff;
+
+/// Reporting how much of a ver_stub section each member uses.
+mod usage;
+
+use show::OutputFormat;
+use verify::{Expectations, parse_duration, parse_member};
 
 /// Inject git and build metadata into binaries via the ver_stub linker section.
 ///
@@ -17,6 +39,7 @@ use ver_stub_build::{LinkSection, LlvmTools, platform_section_name};
 /// For reproducible builds:
 /// - VER_STUB_IDEMPOTENT: If set, build timestamp/date are never included (always None)
 /// - VER_STUB_BUILD_TIME: Override build timestamp with a fixed value (unix or RFC 3339)
+/// - SOURCE_DATE_EPOCH: Used like VER_STUB_BUILD_TIME if that isn't set (unix timestamp only)
 #[derive(Debug, Conf)]
 struct Args {
     /// Include git SHA (git rev-parse HEAD)
@@ -63,6 +86,31 @@ struct Args {
     #[conf(long)]
     custom: Option<String>,
 
+    /// Named field to include, as key=value (read with ver_stub::field). Can be repeated.
+    #[conf(repeat, long, value_parser = parse_field)]
+    field: Vec<(String, String)>,
+
+    /// Warn if the section data uses more than this percentage of the section (default 80)
+    #[conf(long)]
+    usage_warning: Option<u8>,
+
+    /// What to do if the data doesn't fit in the section: error, truncate (shorten the
+    /// lowest priority free-text members, and leave out the others), or drop (leave out
+    /// the lowest priority members)
+    #[conf(long, default_value = "error", value_parser = parse_overflow_policy)]
+    on_overflow: OverflowPolicy,
+
+    /// Member to keep when the data doesn't fit, most important first, e.g. git_sha.
+    /// Can be repeated. Unlisted members are cut first, commit message before anything else.
+    #[conf(repeat, long, value_parser = parse_member)]
+    priority: Vec<Member>,
+
+    /// Section format version to write: 1 (default) is readable by every version of ver-stub;
+    /// 2 starts with a marker, so the data can be found without section headers; 3 adds u32
+    /// offsets for sections larger than 64 KiB (default for those)
+    #[conf(long, value_parser = parse_format_version)]
+    format_version: Option<SectionFormat>,
+
     /// Output path (writes to this path, or {path}/ver_stub_data if it's a directory).
     /// Mutually exclusive with subcommands.
     #[conf(short, long)]
@@ -82,7 +130,12 @@ enum Command {
     /// the requested version info, and writes the result to {input}.bin
     /// (or to the specified output path).
     ///
+    /// Firmware images (objcopy -O binary, ihex or srec output) can be patched with
+    /// --image. The section is found at its address in the ELF file given with --elf,
+    /// or else by the marker at the start of previously written version data.
+    ///
     /// Requires llvm-tools: rustup component add llvm-tools
+    /// (unless --backend native or --image is used)
     Patch {
         /// Path to the binary to patch (e.g., target/release/my-bin)
         #[conf(pos)]
@@ -93,9 +146,79 @@ enum Command {
         #[conf(short, long)]
         output: Option<PathBuf>,
 
+        /// Patch the input binary itself, via a temporary file which is atomically renamed
+        /// over it. Permissions and modification time are preserved.
+        /// Mutually exclusive with --output.
+        #[conf(long)]
+        in_place: bool,
+
+        /// Do a dry run and log objcopy commands rather than actually executing them
+        #[conf(long)]
+        dry_run: bool,
+
+        /// Patching backend: llvm (llvm-objcopy), or native (overwrite the section
+        /// in place by parsing the headers, doesn't need llvm-tools)
+        #[conf(long, default_value = "llvm", value_parser = parse_backend)]
+        backend: PatchBackend,
+
+        /// Treat the input as a firmware image: raw (objcopy -O binary), ihex, or srec
+        #[conf(long, value_parser = parse_image_format)]
+        image: Option<ImageFormat>,
+
+        /// ELF file the image was made from, used to find the section's address
+        #[conf(long)]
+        elf: Option<PathBuf>,
+
+        /// Address of the first byte of a raw image, e.g. 0x08000000.
+        /// Defaults to the lowest address of the ELF file's loaded sections.
+        #[conf(long, value_parser = parse_address)]
+        base_address: Option<u64>,
+
+        /// Size of the section in bytes, required for an image without --elf, where the
+        /// section is found by its marker. Must match the firmware's VER_STUB_BUFFER_SIZE.
+        #[conf(long)]
+        section_size: Option<usize>,
+
+        /// Print a JSON report of what was written (section size and usage, member
+        /// values, output path) to stdout
+        #[conf(long)]
+        json: bool,
+    },
+
+    /// Fill the ver_stub section of a binary with zeroes, undoing `patch`.
+    ///
+    /// Example: ver-stub zero target/release/my-bin.bin -o target/release/my-bin
+    ///
+    /// An all-zero section reads as "all members absent", which is how the section
+    /// is in a freshly linked binary. With --backend native, the result is byte-identical
+    /// to the binary before it was patched (if it was also patched natively).
+    ///
+    /// Requires llvm-tools: rustup component add llvm-tools
+    /// (unless --backend native is used)
+    #[conf(alias = "unpatch")]
+    Zero {
+        /// Path to the binary to unpatch
+        #[conf(pos)]
+        input: PathBuf,
+
+        /// Output file path, or directory to write {input_name}.bin in.
+        /// Either this or --in-place is required.
+        #[conf(short, long)]
+        output: Option<PathBuf>,
+
+        /// Unpatch the input binary itself, via a temporary file which is atomically renamed
+        /// over it. Permissions and modification time are preserved.
+        #[conf(long)]
+        in_place: bool,
+
         /// Do a dry run and log objcopy commands rather than actually executing them
         #[conf(long)]
         dry_run: bool,
+
+        /// Patching backend: llvm (llvm-objcopy), or native (overwrite the section
+        /// in place by parsing the headers, doesn't need llvm-tools)
+        #[conf(long, default_value = "llvm", value_parser = parse_backend)]
+        backend: PatchBackend,
     },
 
     /// Print the platform-specific linker section name *for the host platform* and exit.
@@ -114,6 +237,150 @@ enum Command {
         #[conf(pos)]
         input: PathBuf,
     },
+
+    /// Decode the ver_stub section of a binary and print every member.
+    ///
+    /// Example: ver-stub show target/release/my-bin --format json
+    ///
+    /// The section is found by parsing the headers directly, so the binary is never
+    /// executed, and llvm-tools aren't needed. Works with ELF, Mach-O and PE binaries.
+    ///
+    /// With --scan, the file is instead searched for the marker at the start of the
+    /// section data, which works with raw images (e.g. objcopy -O binary output) and
+    /// core dumps.
+    #[conf(alias = "read")]
+    Show {
+        /// Path to the binary to inspect
+        #[conf(pos)]
+        input: PathBuf,
+
+        /// Find the section data by scanning the file for its marker, instead of by section name
+        #[conf(long)]
+        scan: bool,
+
+        /// Output format: table, json, or env (VER_STUB_<NAME>=value lines)
+        #[conf(long, default_value = "table")]
+        format: OutputFormat,
+    },
+
+    /// Show how much of the ver_stub section of a binary each member uses, and how much is free.
+    ///
+    /// Example: ver-stub usage target/release/my-bin
+    Usage {
+        /// Path to the binary to inspect
+        #[conf(pos)]
+        input: PathBuf,
+    },
+
+    /// Check the ver_stub section of a binary against expected values, e.g. in CI.
+    ///
+    /// Example: ver-stub verify target/release/my-bin --expect-git-sha $(git rev-parse HEAD) --forbid-dirty
+    ///
+    /// Exits with a non-zero status and lists every mismatch if any check fails.
+    Verify {
+        /// Path to the binary to check
+        #[conf(pos)]
+        input: PathBuf,
+
+        /// Find the section data by scanning the file for its marker, instead of by section name
+        #[conf(long)]
+        scan: bool,
+
+        /// Expected git SHA (the full SHA, as printed by git rev-parse HEAD)
+        #[conf(long)]
+        expect_git_sha: Option<String>,
+
+        /// Expected git branch
+        #[conf(long)]
+        expect_branch: Option<String>,
+
+        /// Require a member to be present, e.g. git_describe. Can be repeated.
+        #[conf(repeat, long, value_parser = parse_member)]
+        require: Vec<Member>,
+
+        /// Fail if git describe is not set, or ends with -dirty
+        #[conf(long)]
+        forbid_dirty: bool,
+
+        /// Fail if the build timestamp is not set, or is older than this, e.g. 90d
+        /// (units: s, m, h, d)
+        #[conf(long, value_parser = parse_duration)]
+        max_build_age: Option<Duration>,
+    },
+
+    /// Compare two builds of a binary, ignoring the contents of their ver_stub sections.
+    ///
+    /// Example: ver-stub repro-diff build-1/my-bin build-2/my-bin
+    ///
+    /// Prints which ver_stub members differ, and the sha256 of each binary with its
+    /// ver_stub section zeroed. If the binaries differ in anything other than the
+    /// ver_stub section, lists the other sections which differ, and exits with a
+    /// non-zero status.
+    ///
+    /// The headers are parsed directly, so this doesn't need llvm-tools.
+    ReproDiff {
+        /// Path to the first binary
+        #[conf(pos)]
+        a: PathBuf,
+
+        /// Path to the second binary
+        #[conf(pos)]
+        b: PathBuf,
+    },
+}
+
+fn parse_backend(s: &str) -> Result<PatchBackend, String> {
+    match s {
+        "llvm" => Ok(PatchBackend::Llvm),
+        "native" => Ok(PatchBackend::Native),
+        _ => Err(format!(
+            "unknown backend '{s}', expected one of: llvm, native"
+        )),
+    }
+}
+
+fn parse_overflow_policy(s: &str) -> Result<OverflowPolicy, String> {
+    match s {
+        "error" => Ok(OverflowPolicy::Error),
+        "truncate" => Ok(OverflowPolicy::Truncate),
+        "drop" => Ok(OverflowPolicy::Drop),
+        _ => Err(format!(
+            "unknown overflow policy '{s}', expected one of: error, truncate, drop"
+        )),
+    }
+}
+
+fn parse_image_format(s: &str) -> Result<ImageFormat, String> {
+    match s {
+        "raw" => Ok(ImageFormat::Raw),
+        "ihex" => Ok(ImageFormat::IntelHex),
+        "srec" => Ok(ImageFormat::SRecord),
+        _ => Err(format!(
+            "unknown image format '{s}', expected one of: raw, ihex, srec"
+        )),
+    }
+}
+
+fn parse_address(s: &str) -> Result<u64, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| format!("invalid address '{s}', expected e.g. 0x08000000"))
+}
+
+fn parse_field(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("invalid field '{s}', expected key=value")),
+    }
+}
+
+fn parse_format_version(s: &str) -> Result<SectionFormat, String> {
+    s.parse()
+        .ok()
+        .and_then(SectionFormat::from_version)
+        .ok_or_else(|| format!("unknown format version '{s}', expected one of: 1, 2, 3"))
 }
 
 fn build_section(args: &Args) -> LinkSection {
@@ -160,9 +427,82 @@ fn build_section(args: &Args) -> LinkSection {
         section = section.with_custom(custom);
     }
 
+    for (key, value) in &args.field {
+        section = section.with_field(key, value);
+    }
+
+    if let Some(percent) = args.usage_warning {
+        section = section.with_usage_warning(percent);
+    }
+
+    section = section
+        .with_overflow_policy(args.on_overflow)
+        .with_priority(args.priority.iter().copied());
+
+    if let Some(format) = args.format_version {
+        section = section.with_section_format(format);
+    }
+
     section
 }
 
+/// Unwraps a result, or exits with an error message.
+fn exit_on_error<T>(result: Result<T, ver_stub_build::Error>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    })
+}
+
+/// Finds the LLVM tools, or exits with an error message.
+fn llvm_tools_or_exit() -> LlvmTools {
+    LlvmTools::new().unwrap_or_else(|e| {
+        eprintln!("error: could not find LLVM tools: {}", e);
+        eprintln!("Please install llvm-tools: rustup component add llvm-tools");
+        std::process::exit(1);
+    })
+}
+
+/// Reads the ver_stub section bytes from a binary by parsing its headers, or exits with
+/// an error message.
+fn read_section_or_exit(input: &Path) -> (String, Vec<u8>) {
+    let binary = binary::Binary::read(input).unwrap_or_else(|e| {
+        eprintln!(
+            "error: failed to read section from {}: {}",
+            input.display(),
+            e
+        );
+        std::process::exit(1);
+    });
+    (
+        binary.ver_stub_name().to_owned(),
+        binary.ver_stub_bytes().to_vec(),
+    )
+}
+
+/// Reads the ver_stub section data from a binary, either by section name or by scanning
+/// the file for its marker, or exits with an error message.
+fn read_section_data_or_exit(input: &Path, scan: bool) -> Vec<u8> {
+    if !scan {
+        let (_section_name, bytes) = read_section_or_exit(input);
+        return bytes;
+    }
+
+    let image = std::fs::read(input).unwrap_or_else(|e| {
+        eprintln!("error: failed to read {}: {}", input.display(), e);
+        std::process::exit(1);
+    });
+    let Some(reader) = SectionReader::find(&image) else {
+        eprintln!(
+            "error: no ver_stub section data found in {} \
+             (sections in format version 1 can't be found by scanning)",
+            input.display()
+        );
+        std::process::exit(1);
+    };
+    reader.as_bytes().to_vec()
+}
+
 fn main() {
     // Unset OUT_DIR to prevent LinkSection from trying to use build.rs paths
     // SAFETY: We're single-threaded at this point, before any other code runs
@@ -184,26 +524,98 @@ fn main() {
         Some(Command::Patch {
             ref input,
             ref output,
+            in_place,
             dry_run,
+            backend,
+            image,
+            ref elf,
+            base_address,
+            section_size,
+            json,
         }) => {
-            let output_path = output
-                .clone()
-                .unwrap_or_else(|| input.parent().unwrap().to_path_buf());
-            section
+            let mut cmd = section
                 .patch_into(input)
                 .dry_run(dry_run)
-                .write_to(&output_path)
-                .unwrap();
+                .with_backend(backend);
+            match (image, elf) {
+                (_, Some(_)) if section_size.is_some() => {
+                    eprintln!("error: --section-size can't be used with --elf");
+                    std::process::exit(1);
+                }
+                (Some(format), Some(elf)) => {
+                    let locator = ImageLocator::Elf {
+                        path: elf.clone(),
+                        base_address,
+                    };
+                    cmd = cmd.with_image(format, locator);
+                }
+                (Some(_), None) if base_address.is_some() => {
+                    eprintln!("error: --base-address requires --elf");
+                    std::process::exit(1);
+                }
+                (Some(format), None) => {
+                    let Some(size) = section_size else {
+                        eprintln!(
+                            "error: --section-size is required to patch an image without --elf"
+                        );
+                        std::process::exit(1);
+                    };
+                    cmd = cmd.with_image(format, ImageLocator::Marker { size });
+                }
+                (None, _) if elf.is_some() || base_address.is_some() || section_size.is_some() => {
+                    eprintln!("error: --elf, --base-address and --section-size require --image");
+                    std::process::exit(1);
+                }
+                (None, _) => {}
+            }
+            let report = if in_place {
+                if output.is_some() {
+                    eprintln!("error: --in-place and --output are mutually exclusive");
+                    std::process::exit(1);
+                }
+                exit_on_error(cmd.in_place())
+            } else {
+                let output_path = output
+                    .clone()
+                    .unwrap_or_else(|| input.parent().unwrap().to_path_buf());
+                exit_on_error(cmd.write_to(&output_path))
+            };
+            if json {
+                print!("{}", report.to_json());
+            }
+        }
+        Some(Command::Zero {
+            ref input,
+            ref output,
+            in_place,
+            dry_run,
+            backend,
+        }) => {
+            let cmd = UpdateSectionCommand::zero_section(input)
+                .dry_run(dry_run)
+                .with_backend(backend);
+            match (output, in_place) {
+                (Some(_), true) => {
+                    eprintln!("error: --in-place and --output are mutually exclusive");
+                    std::process::exit(1);
+                }
+                (Some(output), false) => {
+                    exit_on_error(cmd.write_to(output));
+                }
+                (None, true) => {
+                    exit_on_error(cmd.in_place());
+                }
+                (None, false) => {
+                    eprintln!("error: one of --output or --in-place is required");
+                    std::process::exit(1);
+                }
+            }
         }
         Some(Command::PrintHostSectionName) => {
             println!("{}", ver_stub_build::SECTION_NAME);
         }
         Some(Command::GetSectionInfo { ref input }) => {
-            let llvm = LlvmTools::new().unwrap_or_else(|e| {
-                eprintln!("error: could not find LLVM tools: {}", e);
-                eprintln!("Please install llvm-tools: rustup component add llvm-tools");
-                std::process::exit(1);
-            });
+            let llvm = llvm_tools_or_exit();
             let (binary_format, section_name, info) = llvm
                 .get_section_info(input, platform_section_name)
                 .unwrap_or_else(|e| {
@@ -221,12 +633,82 @@ fn main() {
                 None => println!("(not found)"),
             }
         }
+        Some(Command::Show {
+            ref input,
+            scan,
+            format,
+        }) => {
+            let bytes = read_section_data_or_exit(input, scan);
+            let members = show::decode_members(&bytes).unwrap_or_else(|e| {
+                eprintln!("error: malformed section in {}: {}", input.display(), e);
+                std::process::exit(1);
+            });
+            print!("{}", show::render(&members, format));
+        }
+        Some(Command::Usage { ref input }) => {
+            let (section_name, bytes) = read_section_or_exit(input);
+            let table = usage::render(&bytes).unwrap_or_else(|e| {
+                eprintln!("error: malformed section in {}: {}", input.display(), e);
+                std::process::exit(1);
+            });
+            println!("section: {} ({} bytes)", section_name, bytes.len());
+            println!();
+            print!("{}", table);
+        }
+        Some(Command::Verify {
+            ref input,
+            scan,
+            ref expect_git_sha,
+            ref expect_branch,
+            ref require,
+            forbid_dirty,
+            max_build_age,
+        }) => {
+            let bytes = read_section_data_or_exit(input, scan);
+            let members = show::decode_members(&bytes).unwrap_or_else(|e| {
+                eprintln!("error: malformed section in {}: {}", input.display(), e);
+                std::process::exit(1);
+            });
+            let expectations = Expectations {
+                git_sha: expect_git_sha.clone(),
+                git_branch: expect_branch.clone(),
+                required: require.clone(),
+                forbid_dirty,
+                max_build_age,
+            };
+            let mismatches = verify::check(&members, &expectations, SystemTime::now());
+            if !mismatches.is_empty() {
+                eprintln!("error: {} failed verification:", input.display());
+                for mismatch in &mismatches {
+                    eprintln!("  {}", mismatch.to_string().replace('\n', "\n  "));
+                }
+                std::process::exit(1);
+            }
+            eprintln!("ver-stub: {} passed verification", input.display());
+        }
+        Some(Command::ReproDiff { ref a, ref b }) => {
+            let read = |path: &Path| {
+                binary::Binary::read(path).unwrap_or_else(|e| {
+                    eprintln!("error: failed to read {}: {}", path.display(), e);
+                    std::process::exit(1);
+                })
+            };
+            let (a, b) = (read(a), read(b));
+            let report = repro_diff::Report::new(&a, &b).unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            });
+            print!("{}", report);
+            if !report.is_identical() {
+                std::process::exit(1);
+            }
+        }
         None => {
             let Some(output) = args.output else {
                 eprintln!("error: --output is required when not using a subcommand");
                 std::process::exit(1);
             };
-            let output_path = section.write_to(&output).unwrap();
+            let output_path = exit_on_error(section.write_to(&output));
             eprintln!("ver-stub: wrote {}", output_path.display());
         }
     }
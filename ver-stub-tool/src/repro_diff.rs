@@ -0,0 +1,184 @@
+//! Comparing two binaries while ignoring the contents of their ver_stub sections.
+
+use sha2::{Digest, Sha256};
+use std::fmt::{self, Write};
+use ver_stub_build::Member;
+
+use crate::binary::{Binary, Section};
+use crate::show;
+
+/// A section which differs between the two binaries.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SectionDiff {
+    /// The section is in both binaries, with different contents
+    Contents { name: String },
+    /// The section is in only one of the binaries
+    OnlyIn { name: String, which: char },
+}
+
+impl fmt::Display for SectionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Contents { name } => write!(f, "{name}: contents differ"),
+            Self::OnlyIn { name, which } => write!(f, "{name}: only in {which}"),
+        }
+    }
+}
+
+/// The result of comparing two binaries.
+pub struct Report<'a> {
+    a: &'a Binary,
+    b: &'a Binary,
+    members: Vec<(Member, Option<&'a str>, Option<&'a str>)>,
+    hashes: (String, String),
+    identical: bool,
+    sections: Vec<SectionDiff>,
+}
+
+impl<'a> Report<'a> {
+    /// Compares two binaries, ignoring the contents of their ver_stub sections.
+    pub fn new(a: &'a Binary, b: &'a Binary) -> Result<Self, String> {
+        let decode = |binary: &'a Binary| {
+            show::decode_members(binary.ver_stub_bytes())
+                .map_err(|e| format!("malformed section in {}: {}", binary.path().display(), e))
+        };
+        let members = decode(a)?
+            .into_iter()
+            .zip(decode(b)?)
+            .map(|((member, a), (_, b))| (member, a, b))
+            .collect();
+
+        let (zeroed_a, zeroed_b) = (a.zeroed(), b.zeroed());
+        let hashes = (sha256_hex(&zeroed_a), sha256_hex(&zeroed_b));
+        let identical = zeroed_a == zeroed_b;
+
+        let sections = if identical {
+            Vec::new()
+        } else {
+            let other_sections = |binary: &'a Binary| {
+                binary.other_sections().map_err(|e| {
+                    format!(
+                        "failed to read sections of {}: {}",
+                        binary.path().display(),
+                        e
+                    )
+                })
+            };
+            diff_sections(&other_sections(a)?, &other_sections(b)?)
+        };
+
+        Ok(Self {
+            a,
+            b,
+            members,
+            hashes,
+            identical,
+            sections,
+        })
+    }
+
+    /// Returns true if the binaries only differ in their ver_stub sections.
+    pub fn is_identical(&self) -> bool {
+        self.identical
+    }
+}
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "a: {}", self.a.path().display())?;
+        writeln!(f, "b: {}", self.b.path().display())?;
+
+        writeln!(f)?;
+        writeln!(f, "ver_stub section:")?;
+        let width = Member::ALL
+            .iter()
+            .map(|m| m.name().len())
+            .max()
+            .unwrap_or(0);
+        for (member, a, b) in &self.members {
+            let name = member.name();
+            if a == b {
+                if let Some(value) = a {
+                    writeln!(f, "  {name:<width$}  same: {value}")?;
+                }
+            } else {
+                let a = a.unwrap_or("(not set)");
+                let b = b.unwrap_or("(not set)");
+                writeln!(f, "  {name:<width$}  a: {a}")?;
+                writeln!(f, "  {:<width$}  b: {b}", "")?;
+            }
+        }
+
+        writeln!(f)?;
+        writeln!(f, "sha256 with ver_stub section zeroed:")?;
+        writeln!(f, "  a: {}", self.hashes.0)?;
+        writeln!(f, "  b: {}", self.hashes.1)?;
+
+        writeln!(f)?;
+        if self.identical {
+            writeln!(f, "binaries are identical apart from the ver_stub section")
+        } else if self.sections.is_empty() {
+            writeln!(
+                f,
+                "binaries differ outside the ver_stub section, but no section contents \
+                 differ (e.g. in the headers, padding or trailing data)"
+            )
+        } else {
+            writeln!(f, "binaries differ outside the ver_stub section:")?;
+            for section in &self.sections {
+                writeln!(f, "  {section}")?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Matches sections up by name, and lists those which differ.
+///
+/// Names are not necessarily unique (e.g. in relocatable object files), so the
+/// nth section with a given name in `a` is compared with the nth one in `b`.
+fn diff_sections(a: &[Section], b: &[Section]) -> Vec<SectionDiff> {
+    fn nth(sections: &[Section], i: usize) -> usize {
+        let name = sections[i].0;
+        sections[..i].iter().filter(|(n, _)| *n == name).count()
+    }
+    fn find<'a>(sections: &[Section<'a>], name: &str, n: usize) -> Option<Option<&'a [u8]>> {
+        sections
+            .iter()
+            .filter(|(other, _)| *other == name)
+            .nth(n)
+            .map(|(_, contents)| *contents)
+    }
+
+    let mut diffs = Vec::new();
+    for (i, (name, contents)) in a.iter().enumerate() {
+        match find(b, name, nth(a, i)) {
+            Some(other) if other == *contents => {}
+            Some(_) => diffs.push(SectionDiff::Contents {
+                name: name.to_string(),
+            }),
+            None => diffs.push(SectionDiff::OnlyIn {
+                name: name.to_string(),
+                which: 'a',
+            }),
+        }
+    }
+    for (i, (name, _)) in b.iter().enumerate() {
+        if find(a, name, nth(b, i)).is_none() {
+            diffs.push(SectionDiff::OnlyIn {
+                name: name.to_string(),
+                which: 'b',
+            });
+        }
+    }
+    diffs
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .fold(String::with_capacity(64), |mut out, b| {
+            write!(out, "{b:02x}").unwrap();
+            out
+        })
+}
@@ -0,0 +1,96 @@
+//! Decoding and printing the contents of a ver_stub section.
+
+use std::fmt::Write;
+use std::str::FromStr;
+use ver_stub_build::{Fields, Member, SectionError, SectionReader, json_string, member_env_var};
+
+/// Output format for the `show` subcommand.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Aligned `name  value` lines, with named fields as `key=value` pairs
+    #[default]
+    Table,
+    /// A single JSON object, with `null` for absent members, and named fields as an object
+    Json,
+    /// `VER_STUB_<NAME>=value` lines, for present members only
+    Env,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            "env" => Ok(Self::Env),
+            _ => Err(format!(
+                "unknown format '{s}', expected one of: table, json, env"
+            )),
+        }
+    }
+}
+
+/// Decodes every known member of the section.
+pub fn decode_members(section_bytes: &[u8]) -> Result<Vec<(Member, Option<&str>)>, SectionError> {
+    let reader = SectionReader::new(section_bytes);
+    Member::ALL
+        .iter()
+        .map(|member| Ok((*member, reader.get(*member)?)))
+        .collect()
+}
+
+/// Renders decoded members in the requested format.
+pub fn render(members: &[(Member, Option<&str>)], format: OutputFormat) -> String {
+    let mut out = String::new();
+    match format {
+        OutputFormat::Table => {
+            let width = members
+                .iter()
+                .map(|(member, _)| member.name().len())
+                .max()
+                .unwrap_or(0);
+            for (member, value) in members {
+                let value = match value {
+                    Some(value) if *member == Member::Fields => Fields::new(value)
+                        .map(|(key, value)| format!("{key}={value}"))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    Some(value) => value.to_string(),
+                    None => "(not set)".to_string(),
+                };
+                writeln!(out, "{:<width$}  {}", member.name(), value).unwrap();
+            }
+        }
+        OutputFormat::Json => {
+            out.push('{');
+            for (i, (member, value)) in members.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write!(out, "\n  {}: ", json_string(member.name())).unwrap();
+                match value {
+                    Some(value) if *member == Member::Fields => {
+                        let fields: Vec<_> = Fields::new(value)
+                            .map(|(key, value)| {
+                                format!("{}: {}", json_string(key), json_string(value))
+                            })
+                            .collect();
+                        write!(out, "{{{}}}", fields.join(", ")).unwrap();
+                    }
+                    Some(value) => out.push_str(&json_string(value)),
+                    None => out.push_str("null"),
+                }
+            }
+            out.push_str("\n}\n");
+        }
+        OutputFormat::Env => {
+            for (member, value) in members {
+                if let Some(value) = value {
+                    writeln!(out, "{}={}", member_env_var(*member), value).unwrap();
+                }
+            }
+        }
+    }
+    out
+}
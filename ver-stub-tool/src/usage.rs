@@ -0,0 +1,63 @@
+//! Reporting how much of a ver_stub section each member uses.
+
+use std::fmt::Write;
+use ver_stub_build::{SectionError, SectionReader};
+
+use crate::show;
+
+/// Renders a table of the bytes used by the header and each member, and the free space.
+pub fn render(section_bytes: &[u8]) -> Result<String, SectionError> {
+    let members = show::decode_members(section_bytes)?;
+    let reader = SectionReader::new(section_bytes);
+    let used = reader.used_len()?;
+    let size = section_bytes.len();
+
+    let mut rows: Vec<(&str, usize)> = Vec::new();
+    if used > 0 {
+        rows.push(("(header)", reader.header_len()?));
+    }
+    for (member, value) in &members {
+        rows.push((member.name(), value.map_or(0, str::len)));
+    }
+    // Members written by a newer version of ver-stub-build, which this one doesn't know
+    let accounted: usize = rows.iter().map(|(_, len)| len).sum();
+    if used > accounted {
+        rows.push(("(unknown members)", used - accounted));
+    }
+
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain(["(total)".len()])
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for (name, len) in &rows {
+        writeln!(out, "{name:<width$}  {len:>5}  {}", percent(*len, size)).unwrap();
+    }
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "{:<width$}  {used:>5}  {}",
+        "used",
+        percent(used, size)
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "{:<width$}  {:>5}  {}",
+        "free",
+        size - used,
+        percent(size - used, size)
+    )
+    .unwrap();
+    writeln!(out, "{:<width$}  {size:>5}", "(total)").unwrap();
+    Ok(out)
+}
+
+fn percent(len: usize, size: usize) -> String {
+    if size == 0 {
+        return String::new();
+    }
+    format!("{:>5.1}%", len as f64 * 100.0 / size as f64)
+}
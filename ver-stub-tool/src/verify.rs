@@ -0,0 +1,319 @@
+//! Checking the contents of a ver_stub section against expected values.
+
+use std::fmt;
+use std::time::{Duration, SystemTime};
+use ver_stub_build::{Describe, Member, Timestamp};
+
+/// The values a section is expected to contain, from the command line.
+#[derive(Debug, Default)]
+pub struct Expectations {
+    /// Expected value of `git_sha`
+    pub git_sha: Option<String>,
+    /// Expected value of `git_branch`
+    pub git_branch: Option<String>,
+    /// Members which must be present
+    pub required: Vec<Member>,
+    /// Whether `git_describe` must be present, and not end in `-dirty`
+    pub forbid_dirty: bool,
+    /// If set, `build_timestamp` must be present, and at most this long ago
+    pub max_build_age: Option<Duration>,
+}
+
+/// A way in which a section doesn't match the expectations.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    /// A member has a different value than expected
+    Value {
+        member: Member,
+        expected: String,
+        actual: Option<String>,
+    },
+    /// A required member is not present
+    Missing { member: Member },
+    /// The git describe output has a `-dirty` suffix
+    Dirty { describe: String },
+    /// A member which has to be parsed to be checked isn't valid
+    Malformed { member: Member, value: String },
+    /// The binary was built longer ago than allowed
+    TooOld {
+        build_timestamp: String,
+        max_age: Duration,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Value {
+                member,
+                expected,
+                actual,
+            } => {
+                writeln!(f, "{}:", member.name())?;
+                writeln!(f, "    expected: {}", expected)?;
+                write!(
+                    f,
+                    "    actual:   {}",
+                    actual.as_deref().unwrap_or("(not set)")
+                )
+            }
+            Self::Missing { member } => write!(f, "{}: required, but not set", member.name()),
+            Self::Dirty { describe } => {
+                write!(f, "git_describe: '{}' is dirty", describe)
+            }
+            Self::Malformed { member, value } => {
+                write!(f, "{}: '{}' is not valid", member.name(), value)
+            }
+            Self::TooOld {
+                build_timestamp,
+                max_age,
+            } => write!(
+                f,
+                "build_timestamp: '{}' is more than {}s ago",
+                build_timestamp,
+                max_age.as_secs()
+            ),
+        }
+    }
+}
+
+/// Checks decoded members against the expectations, returning every mismatch.
+///
+/// The age of the build is relative to `now`.
+pub fn check(
+    members: &[(Member, Option<&str>)],
+    expectations: &Expectations,
+    now: SystemTime,
+) -> Vec<Mismatch> {
+    let get = |member: Member| {
+        members
+            .iter()
+            .find(|(m, _)| *m == member)
+            .and_then(|(_, value)| *value)
+    };
+    let mut mismatches = Vec::new();
+
+    let expected_values = [
+        (Member::GitSha, &expectations.git_sha),
+        (Member::GitBranch, &expectations.git_branch),
+    ];
+    for (member, expected) in expected_values {
+        if let Some(expected) = expected
+            && get(member) != Some(expected.as_str())
+        {
+            mismatches.push(Mismatch::Value {
+                member,
+                expected: expected.clone(),
+                actual: get(member).map(str::to_string),
+            });
+        }
+    }
+
+    // Checking for -dirty requires git_describe to be present, and checking the age
+    // requires build_timestamp
+    let mut required = expectations.required.clone();
+    if expectations.forbid_dirty {
+        required.push(Member::GitDescribe);
+    }
+    if expectations.max_build_age.is_some() {
+        required.push(Member::BuildTimestamp);
+    }
+    for member in Member::ALL {
+        if required.contains(&member) && get(member).is_none() {
+            mismatches.push(Mismatch::Missing { member });
+        }
+    }
+
+    if expectations.forbid_dirty
+        && let Some(describe) = get(Member::GitDescribe)
+        && Describe::parse(describe).is_some_and(|d| d.is_dirty())
+    {
+        mismatches.push(Mismatch::Dirty {
+            describe: describe.to_string(),
+        });
+    }
+
+    if let Some(max_age) = expectations.max_build_age
+        && let Some(build_timestamp) = get(Member::BuildTimestamp)
+    {
+        // In whole seconds, which is the precision of build timestamps anyway
+        let now_secs = now
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX));
+        let max_secs = i64::try_from(max_age.as_secs()).unwrap_or(i64::MAX);
+        match Timestamp::parse(build_timestamp) {
+            Some(ts) if now_secs.saturating_sub(ts.unix_seconds()) > max_secs => {
+                mismatches.push(Mismatch::TooOld {
+                    build_timestamp: build_timestamp.to_string(),
+                    max_age,
+                });
+            }
+            Some(_) => {}
+            None => mismatches.push(Mismatch::Malformed {
+                member: Member::BuildTimestamp,
+                value: build_timestamp.to_string(),
+            }),
+        }
+    }
+
+    mismatches
+}
+
+/// Parses a duration with a unit suffix: `s`, `m`, `h` or `d`, e.g. `90d`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let err =
+        || format!("invalid duration '{s}', expected a number and a unit (s, m, h or d), e.g. 90d");
+    let (number, unit) = s.split_at(s.len().checked_sub(1).ok_or_else(err)?);
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return Err(err()),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit_secs))
+        .map(Duration::from_secs)
+        .ok_or_else(err)
+}
+
+/// Parses a member name, as printed by `ver-stub show` (e.g. `git_describe`).
+pub fn parse_member(s: &str) -> Result<Member, String> {
+    Member::ALL
+        .into_iter()
+        .find(|member| member.name() == s)
+        .ok_or_else(|| {
+            let names: Vec<_> = Member::ALL.iter().map(|m| m.name()).collect();
+            format!(
+                "unknown member '{s}', expected one of: {}",
+                names.join(", ")
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-15T10:30:00Z
+    fn now() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_705_314_600)
+    }
+
+    fn members<'a>(values: &[(Member, &'a str)]) -> Vec<(Member, Option<&'a str>)> {
+        Member::ALL
+            .into_iter()
+            .map(|member| {
+                let value = values.iter().find(|(m, _)| *m == member).map(|(_, v)| *v);
+                (member, value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_check_passes() {
+        let members = members(&[
+            (Member::GitSha, "0123456789"),
+            (Member::GitDescribe, "v1.2.3-4-gabcdef0"),
+            (Member::BuildTimestamp, "2024-01-14T10:30:00Z"),
+        ]);
+        let expectations = Expectations {
+            git_sha: Some("0123456789".into()),
+            required: vec![Member::GitSha],
+            forbid_dirty: true,
+            max_build_age: Some(Duration::from_secs(2 * 86400)),
+            ..Default::default()
+        };
+        assert_eq!(check(&members, &expectations, now()), []);
+    }
+
+    #[test]
+    fn test_check_value_and_missing() {
+        let members = members(&[(Member::GitSha, "0123456789")]);
+        let expectations = Expectations {
+            git_sha: Some("abcdef".into()),
+            required: vec![Member::BuildDate],
+            ..Default::default()
+        };
+        assert_eq!(
+            check(&members, &expectations, now()),
+            [
+                Mismatch::Value {
+                    member: Member::GitSha,
+                    expected: "abcdef".into(),
+                    actual: Some("0123456789".into()),
+                },
+                Mismatch::Missing {
+                    member: Member::BuildDate
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_dirty() {
+        let expectations = Expectations {
+            forbid_dirty: true,
+            ..Default::default()
+        };
+        let dirty = members(&[(Member::GitDescribe, "v1.2.3-4-gabcdef0-dirty")]);
+        assert_eq!(
+            check(&dirty, &expectations, now()),
+            [Mismatch::Dirty {
+                describe: "v1.2.3-4-gabcdef0-dirty".into()
+            }]
+        );
+
+        // A describe which was left out (e.g. by an overflow policy) can't be checked
+        assert_eq!(
+            check(&members(&[]), &expectations, now()),
+            [Mismatch::Missing {
+                member: Member::GitDescribe
+            }]
+        );
+    }
+
+    #[test]
+    fn test_check_too_old() {
+        let expectations = Expectations {
+            max_build_age: Some(Duration::from_secs(86400)),
+            ..Default::default()
+        };
+        let old = members(&[(Member::BuildTimestamp, "2024-01-13T10:30:00Z")]);
+        assert_eq!(
+            check(&old, &expectations, now()),
+            [Mismatch::TooOld {
+                build_timestamp: "2024-01-13T10:30:00Z".into(),
+                max_age: Duration::from_secs(86400),
+            }]
+        );
+
+        let malformed = members(&[(Member::BuildTimestamp, "2024-01-13")]);
+        assert_eq!(
+            check(&malformed, &expectations, now()),
+            [Mismatch::Malformed {
+                member: Member::BuildTimestamp,
+                value: "2024-01-13".into(),
+            }]
+        );
+
+        assert_eq!(
+            check(&members(&[]), &expectations, now()),
+            [Mismatch::Missing {
+                member: Member::BuildTimestamp
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90d"), Ok(Duration::from_secs(90 * 86400)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("90").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("1.5h").is_err());
+    }
+}
@@ -0,0 +1,43 @@
+//! How long ago the binary was built or committed, relative to the system clock.
+
+use std::time::{Duration, SystemTime};
+
+use crate::{Timestamp, build_timestamp_parsed, git_commit_timestamp_parsed};
+
+/// Returns how long ago the binary was built, if the build timestamp is present.
+///
+/// This is `None` if the build timestamp was not included, e.g. because
+/// `VER_STUB_IDEMPOTENT` was set. If the build timestamp is in the future
+/// (e.g. due to clock skew), this is zero.
+pub fn build_age() -> Option<Duration> {
+    build_timestamp_parsed().map(age)
+}
+
+/// Returns how long ago HEAD was committed, if the git commit timestamp is present.
+///
+/// This uses the author date, like [`git_commit_timestamp`](crate::git_commit_timestamp).
+/// If it is in the future, this is zero.
+pub fn commit_age() -> Option<Duration> {
+    git_commit_timestamp_parsed().map(age)
+}
+
+/// Returns whether the binary was built more than `max_age` ago.
+///
+/// This is `None` if the build timestamp is not present, see [`build_age`].
+///
+/// ```
+/// use std::time::Duration;
+///
+/// if ver_stub::is_older_than(Duration::from_secs(90 * 24 * 3600)) == Some(true) {
+///     eprintln!("This build is more than 90 days old, please upgrade");
+/// }
+/// ```
+pub fn is_older_than(max_age: Duration) -> Option<bool> {
+    build_age().map(|age| age > max_age)
+}
+
+fn age(timestamp: Timestamp) -> Duration {
+    SystemTime::now()
+        .duration_since(timestamp.into())
+        .unwrap_or(Duration::ZERO)
+}
@@ -0,0 +1,182 @@
+//! Structured view of `git describe --always --dirty` output.
+
+use core::fmt;
+
+/// The output of `git describe --always --dirty`, split into its components.
+///
+/// `git describe` produces one of these forms:
+/// - `v1.2.3`: HEAD is exactly at tag `v1.2.3`
+/// - `v1.2.3-4-gabcdef0`: HEAD is 4 commits after tag `v1.2.3`, at commit `abcdef0`
+/// - `abcdef0`: there is no reachable tag, so only the abbreviated hash is printed
+///
+/// Each of these may be followed by `-dirty` if the working tree had uncommitted changes.
+/// Tags may themselves contain hyphens (e.g. `release-2024-01`), so the string is parsed
+/// from the end.
+///
+/// A tag consisting only of hex digits (e.g. `20240101`) is indistinguishable from
+/// the hash-only form, and is parsed as a hash.
+///
+/// ```
+/// use ver_stub::Describe;
+///
+/// let describe = Describe::parse("v1.2.3-4-gabcdef0-dirty").unwrap();
+/// assert_eq!(describe.tag(), Some("v1.2.3"));
+/// assert_eq!(describe.commits_since_tag(), Some(4));
+/// assert_eq!(describe.abbrev_sha(), Some("abcdef0"));
+/// assert!(describe.is_dirty());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Describe<'a> {
+    raw: &'a str,
+    tag: Option<&'a str>,
+    commits_since_tag: u32,
+    abbrev_sha: Option<&'a str>,
+    dirty: bool,
+}
+
+impl<'a> Describe<'a> {
+    /// Parses the output of `git describe --always --dirty`.
+    ///
+    /// Returns `None` if the string is empty.
+    pub fn parse(raw: &'a str) -> Option<Self> {
+        let (rest, dirty) = match raw.strip_suffix("-dirty") {
+            Some(rest) => (rest, true),
+            None => (raw, false),
+        };
+        if rest.is_empty() {
+            return None;
+        }
+
+        let mut result = Self {
+            raw,
+            tag: None,
+            commits_since_tag: 0,
+            abbrev_sha: None,
+            dirty,
+        };
+
+        // <tag>-<n>-g<sha>
+        if let Some((rest, sha)) = rest.rsplit_once("-g")
+            && is_abbrev_sha(sha)
+            && let Some((tag, n)) = rest.rsplit_once('-')
+            && !tag.is_empty()
+            && let Some(n) = parse_distance(n)
+        {
+            result.tag = Some(tag);
+            result.commits_since_tag = n;
+            result.abbrev_sha = Some(sha);
+        } else if is_abbrev_sha(rest) {
+            // <sha>, from --always when there is no tag
+            result.abbrev_sha = Some(rest);
+        } else {
+            // <tag>, when HEAD is exactly at a tag
+            result.tag = Some(rest);
+        }
+
+        Some(result)
+    }
+
+    /// Returns the original string.
+    pub fn as_str(&self) -> &'a str {
+        self.raw
+    }
+
+    /// Returns the most recent tag reachable from HEAD, or `None` if there is none.
+    pub fn tag(&self) -> Option<&'a str> {
+        self.tag
+    }
+
+    /// Returns the number of commits since [`tag`](Self::tag), or `None` if there is no tag.
+    ///
+    /// This is `Some(0)` if HEAD is exactly at the tag.
+    pub fn commits_since_tag(&self) -> Option<u32> {
+        self.tag.map(|_| self.commits_since_tag)
+    }
+
+    /// Returns the abbreviated commit hash.
+    ///
+    /// This is `None` if HEAD is exactly at the tag, since `git describe` doesn't print
+    /// the hash in that case. Use [`git_sha`](crate::git_sha) for the full hash.
+    pub fn abbrev_sha(&self) -> Option<&'a str> {
+        self.abbrev_sha
+    }
+
+    /// Returns true if the working tree had uncommitted changes (a `-dirty` suffix).
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+}
+
+impl fmt::Display for Describe<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.raw)
+    }
+}
+
+// git abbreviates hashes to at least 4 hex digits, and SHA-256 hashes are 64 digits
+fn is_abbrev_sha(s: &str) -> bool {
+    (4..=64).contains(&s.len()) && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+// A decimal number of commits, without sign or leading zeros
+fn parse_distance(s: &str) -> Option<u32> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) || (s.len() > 1 && s.starts_with('0'))
+    {
+        return None;
+    }
+    s.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(s: &str) -> (Option<&str>, Option<u32>, Option<&str>, bool) {
+        let d = Describe::parse(s).unwrap();
+        assert_eq!(d.as_str(), s);
+        (d.tag(), d.commits_since_tag(), d.abbrev_sha(), d.is_dirty())
+    }
+
+    #[test]
+    fn test_parse_describe() {
+        assert_eq!(parts("v1.2.3"), (Some("v1.2.3"), Some(0), None, false));
+        assert_eq!(parts("v1.2.3-dirty"), (Some("v1.2.3"), Some(0), None, true));
+        assert_eq!(
+            parts("v1.2.3-4-gabcdef0"),
+            (Some("v1.2.3"), Some(4), Some("abcdef0"), false)
+        );
+        assert_eq!(
+            parts("v1.2.3-4-gabcdef0-dirty"),
+            (Some("v1.2.3"), Some(4), Some("abcdef0"), true)
+        );
+        assert_eq!(parts("abcdef0"), (None, None, Some("abcdef0"), false));
+        assert_eq!(parts("abcdef0-dirty"), (None, None, Some("abcdef0"), true));
+    }
+
+    #[test]
+    fn test_parse_describe_hyphenated_tags() {
+        assert_eq!(
+            parts("release-2024-01"),
+            (Some("release-2024-01"), Some(0), None, false)
+        );
+        assert_eq!(
+            parts("release-2024-01-12-g0123abcd-dirty"),
+            (Some("release-2024-01"), Some(12), Some("0123abcd"), true)
+        );
+        // A tag that looks like the long form, but isn't
+        assert_eq!(
+            parts("my-tag-gxyz"),
+            (Some("my-tag-gxyz"), Some(0), None, false)
+        );
+        assert_eq!(
+            parts("v1-01-gabcd"),
+            (Some("v1-01-gabcd"), Some(0), None, false)
+        );
+    }
+
+    #[test]
+    fn test_parse_describe_empty() {
+        assert_eq!(Describe::parse(""), None);
+        assert_eq!(Describe::parse("-dirty"), None);
+    }
+}
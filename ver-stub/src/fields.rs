@@ -0,0 +1,86 @@
+//! Named key/value fields stored in the `fields` member.
+
+/// Separates fields from each other in the `fields` member (ASCII record separator).
+pub const FIELD_SEPARATOR: char = '\x1e';
+
+/// Separates the key of a field from its value (ASCII unit separator).
+pub const KEY_VALUE_SEPARATOR: char = '\x1f';
+
+/// Iterator over the named fields of the version data, as `(key, value)` pairs.
+///
+/// Fields are set with `LinkSection::with_field()` in the build script, and stored in
+/// [`Member::Fields`](crate::Member::Fields) as `key\x1fvalue` entries separated by `\x1e`.
+/// Keys and values can't contain either separator.
+///
+/// ```
+/// use ver_stub::Fields;
+///
+/// let mut fields = Fields::new("deploy_env\x1fprod\x1eregion\x1feu-west-1");
+/// assert_eq!(fields.get("region"), Some("eu-west-1"));
+/// assert_eq!(fields.next(), Some(("deploy_env", "prod")));
+/// assert_eq!(fields.next(), Some(("region", "eu-west-1")));
+/// assert_eq!(fields.next(), None);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fields<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Fields<'a> {
+    /// Creates an iterator over the fields encoded in the data of the `fields` member.
+    ///
+    /// Empty entries are skipped, and an entry without a key/value separator is read as
+    /// a key with an empty value.
+    pub const fn new(data: &'a str) -> Self {
+        Self { rest: data }
+    }
+
+    /// Returns the value of the first field with the given key, if present.
+    pub fn get(self, key: &str) -> Option<&'a str> {
+        self.into_iter()
+            .find_map(|(k, value)| (k == key).then_some(value))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+            let (entry, rest) = self
+                .rest
+                .split_once(FIELD_SEPARATOR)
+                .unwrap_or((self.rest, ""));
+            self.rest = rest;
+            if !entry.is_empty() {
+                return Some(entry.split_once(KEY_VALUE_SEPARATOR).unwrap_or((entry, "")));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        assert_eq!(Fields::new("").next(), None);
+        assert_eq!(Fields::default().get("a"), None);
+
+        let fields = Fields::new("\x1ea\x1f1\x1e\x1eb\x1ec\x1f\x1fx\x1ea\x1f2\x1e");
+        let mut iter = fields;
+        assert_eq!(iter.next(), Some(("a", "1")));
+        assert_eq!(iter.next(), Some(("b", "")));
+        assert_eq!(iter.next(), Some(("c", "\x1fx")));
+        assert_eq!(iter.next(), Some(("a", "2")));
+        assert_eq!(iter.next(), None);
+
+        assert_eq!(fields.get("a"), Some("1"));
+        assert_eq!(fields.get("b"), Some(""));
+        assert_eq!(fields.get("d"), None);
+    }
+}
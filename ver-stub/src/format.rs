@@ -0,0 +1,84 @@
+//! Versions of the layout of section data.
+
+/// Marker at the start of a section in format version 2 and later.
+///
+/// It allows finding a section in images without section headers, e.g. the output of
+/// `objcopy -O binary` or a core dump, see [`SectionReader::find`](crate::SectionReader::find).
+///
+/// The first byte can't be mistaken for the `num_members` byte of a [`SectionFormat::V1`]
+/// section, which is never 255.
+pub const SECTION_MAGIC: [u8; 8] = *b"\xffVERSTUB";
+
+/// Layout of the data in a ver_stub section.
+///
+/// Readers detect the format of a section by the presence of [`SECTION_MAGIC`], so
+/// sections in any format can be read by this version of the crate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SectionFormat {
+    /// The original layout, which starts directly with the `num_members` byte.
+    ///
+    /// This is the default, and the only format understood by older versions of this crate,
+    /// which read the leading byte of [`SECTION_MAGIC`] as a member count and panic.
+    #[default]
+    V1,
+    /// [`SECTION_MAGIC`], then the format version byte (2), then the [`V1`](Self::V1) layout.
+    V2,
+    /// Same as [`V2`](Self::V2) (with format version 3), but with `u32` end offsets,
+    /// for sections larger than 64 KiB.
+    V3,
+}
+
+impl SectionFormat {
+    /// Returns the format with the given version number, if it is known.
+    pub const fn from_version(version: u8) -> Option<Self> {
+        match version {
+            1 => Some(Self::V1),
+            2 => Some(Self::V2),
+            3 => Some(Self::V3),
+            _ => None,
+        }
+    }
+
+    /// Returns the version number of the format.
+    pub const fn version(self) -> u8 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+            Self::V3 => 3,
+        }
+    }
+
+    /// Returns the size of each end offset in the header: 2 bytes, or 4 for [`V3`](Self::V3).
+    pub const fn offset_size(self) -> usize {
+        match self {
+            Self::V1 | Self::V2 => 2,
+            Self::V3 => 4,
+        }
+    }
+
+    /// Returns the largest end offset the format can hold, which limits the size of the
+    /// member data.
+    pub const fn max_offset(self) -> usize {
+        match self {
+            Self::V1 | Self::V2 => u16::MAX as usize,
+            Self::V3 => u32::MAX as usize,
+        }
+    }
+
+    /// Returns the size of the header for the given number of members, including the
+    /// magic and the version byte if the format has them.
+    ///
+    /// Member data starts right after the header, and member offsets are relative to it.
+    pub const fn header_size(self, num_members: usize) -> usize {
+        self.prefix_size() + 1 + num_members * self.offset_size()
+    }
+
+    // Number of bytes before the num_members byte
+    pub(crate) const fn prefix_size(self) -> usize {
+        match self {
+            Self::V1 => 0,
+            Self::V2 | Self::V3 => SECTION_MAGIC.len() + 1,
+        }
+    }
+}
@@ -15,16 +15,20 @@
 //! fn build_timestamp() -> Option<&'static str>;
 //! fn build_date() -> Option<&'static str>;
 //! fn custom() -> Option<&'static str>;
+//! fn field(key: &str) -> Option<&'static str>;
 //! ```
 //!
-//! to read fields from the section if they are present.
+//! to read fields from the section if they are present, or [`info()`] to read all of them at once.
+//! [`fields()`] iterates over all named fields set with `LinkSection::with_field()`.
+//! With the `serde` feature, `custom_as::<T>()` deserializes a typed payload set with
+//! `LinkSection::with_custom_serialized()`.
 //!
 //! Then use [`ver-stub-build`](https://docs.rs/ver-stub-build/latest) or [`ver-stub-tool`](https://docs.rs/ver-stub-tool/latest) to write the link section into the
 //! binary at the end of your build.
 //!
 //! ## Details
 //!
-//! The section format is:
+//! The section format (version 1, the default, see [`SectionFormat`]) is:
 //! - First byte: number of members in the section (for forward compatibility)
 //! - Next `num_members * 2` bytes: array of end offsets (u16, little-endian, relative to header)
 //! - Remaining bytes: concatenated string data
@@ -35,37 +39,88 @@
 //! - start = header_size + end[N-1] if N > 0, else header_size
 //! - end = header_size + end[N]
 //! - If start == end, the member is not present.
-//! - If N >= num_members (from first byte), the member is not present.
+//! - If N >= num_members (from the header), the member is not present.
 //!
 //! Using relative offsets means a zero-initialized buffer reads as "all members absent".
 //! The num_members byte enables forward and backwards compatibility: old sections can be read by new code
 //! which has more members added in the future, and new sections can be read by old code as well,
 //! as long as we never change the index of any existing member.
+//!
+//! Version 2 of the format (opt-in) prepends [`SECTION_MAGIC`] and a format version byte, so
+//! the section can be found without section headers. A section which doesn't start with the
+//! magic is read as version 1. Version 3 is the same as version 2, but with u32 end offsets,
+//! for sections larger than 64 KiB. If a section has a format version which this crate
+//! doesn't know, the accessor functions read every member as absent. Older versions of this
+//! crate only understand version 1.
+//!
+//! To decode a section that was extracted from some other binary, use [`SectionReader`].
 
 #![no_std]
 
+#[cfg(feature = "std")]
+extern crate std;
+
+/// Build and commit age, relative to the system clock.
+#[cfg(feature = "std")]
+mod age;
+
+/// Structured view of `git describe` output.
+mod describe;
+
+/// Named key/value fields stored in the `fields` member.
+mod fields;
+
+/// Versions of the layout of section data.
+mod format;
+
+/// Typed custom payloads, decoded from JSON.
+#[cfg(feature = "serde")]
+mod payload;
+
+/// Parsing of section data from arbitrary byte slices.
+mod reader;
+
+/// Helpers shared by the unit tests.
+#[cfg(test)]
+mod test_util;
+
+/// Parsing of RFC 3339 timestamps.
+mod timestamp;
+
+/// Aggregate view of all members of the version data.
+mod version_info;
+
+#[cfg(feature = "std")]
+pub use age::{build_age, commit_age, is_older_than};
+pub use describe::Describe;
+pub use fields::{FIELD_SEPARATOR, Fields, KEY_VALUE_SEPARATOR};
+pub use format::{SECTION_MAGIC, SectionFormat};
+pub use reader::{SectionError, SectionReader};
+pub use timestamp::Timestamp;
+pub use version_info::VersionInfo;
+
 // Size of the version data buffer in bytes.
 // Can be overridden by setting VER_STUB_BUFFER_SIZE env var at compile time.
-// Parsed as u16 since offsets in the header are u16 (max buffer size is 65535).
+// Parsed as u32 since offsets in the header are at most u32 (see `SectionFormat::V3`).
 #[doc(hidden)]
 pub const BUFFER_SIZE: usize = match option_env!("VER_STUB_BUFFER_SIZE") {
-    Some(s) => match u16::from_str_radix(s, 10) {
+    Some(s) => match u32::from_str_radix(s, 10) {
         Ok(n) => n as usize,
-        Err(_) => panic!("VER_STUB_BUFFER_SIZE must be a valid u16 integer (0-65535)"),
+        Err(_) => panic!("VER_STUB_BUFFER_SIZE must be a valid u32 integer (0-4294967295)"),
     },
     None => 512,
 };
 
-// Calculate header size for a given number of members.
-// Header = 1 byte (num_members) + 2 bytes per member (end offsets).
+// Calculate header size for a given number of members, in the largest format with u16 offsets.
+// Header = magic + 1 byte (version) + 1 byte (num_members) + 2 bytes per member (end offsets).
 #[doc(hidden)]
 pub const fn header_size(num_members: usize) -> usize {
-    1 + num_members * 2
+    SectionFormat::V2.header_size(num_members)
 }
 
 // Compile-time checks for buffer size validity.
 // We use 32 as a minimum threshold because:
-// - The header must fit (currently 19 bytes for 9 members)
+// - The header must fit (currently 30 bytes for 10 members)
 // - There must be room for actual data
 // - Anything smaller than 32 bytes is impractical
 // - We want to give clear error messages, so a simpler condition is better.
@@ -77,6 +132,11 @@ const _: () = assert!(
     BUFFER_SIZE > 32,
     "VER_STUB_BUFFER_SIZE must be greater than 32"
 );
+// Buffers larger than u16::MAX are written in `SectionFormat::V3`, whose header is larger.
+const _: () = assert!(
+    BUFFER_SIZE <= u16::MAX as usize || SectionFormat::V3.header_size(Member::COUNT) <= BUFFER_SIZE,
+    "the SectionFormat::V3 header doesn't fit in VER_STUB_BUFFER_SIZE"
+);
 
 /// The section name used for version data (platform-specific).
 ///
@@ -112,36 +172,81 @@ pub const SECTION_NAME: &str = "ver_stub";
 #[used]
 static BUFFER: [u8; BUFFER_SIZE] = [0u8; BUFFER_SIZE];
 
-// Members that can be stored in the version data.
-#[doc(hidden)]
+/// Members that can be stored in the version data.
+///
+/// The discriminant of each member is its index in the section header, and is
+/// never changed once assigned.
 #[repr(u16)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Member {
+    /// See [`git_sha`].
     GitSha = 0,
+    /// See [`git_describe`].
     GitDescribe = 1,
+    /// See [`git_branch`].
     GitBranch = 2,
+    /// See [`git_commit_timestamp`].
     GitCommitTimestamp = 3,
+    /// See [`git_commit_date`].
     GitCommitDate = 4,
+    /// See [`git_commit_msg`].
     GitCommitMsg = 5,
+    /// See [`build_timestamp`].
     BuildTimestamp = 6,
+    /// See [`build_date`].
     BuildDate = 7,
+    /// See [`custom`].
     Custom = 8,
+    /// See [`field`] and [`fields`].
+    Fields = 9,
 }
 
 impl Member {
     /// Number of members in the version data.
     #[doc(hidden)]
-    pub const COUNT: usize = 9;
+    pub const COUNT: usize = 10;
+
+    /// All members, in index order.
+    pub const ALL: [Member; Self::COUNT] = [
+        Member::GitSha,
+        Member::GitDescribe,
+        Member::GitBranch,
+        Member::GitCommitTimestamp,
+        Member::GitCommitDate,
+        Member::GitCommitMsg,
+        Member::BuildTimestamp,
+        Member::BuildDate,
+        Member::Custom,
+        Member::Fields,
+    ];
+
+    /// Returns the name of this member in snake case (e.g. `git_sha`).
+    ///
+    /// This matches the name of the accessor function, and is used by tooling.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Member::GitSha => "git_sha",
+            Member::GitDescribe => "git_describe",
+            Member::GitBranch => "git_branch",
+            Member::GitCommitTimestamp => "git_commit_timestamp",
+            Member::GitCommitDate => "git_commit_date",
+            Member::GitCommitMsg => "git_commit_msg",
+            Member::BuildTimestamp => "build_timestamp",
+            Member::BuildDate => "build_date",
+            Member::Custom => "custom",
+            Member::Fields => "fields",
+        }
+    }
 
     // Reads a member from the version buffer.
     //
     // Returns:
     // - `None` if the member is not present (start == end, or member >= actual num_members)
+    // - `None` if the section was written in a format version newer than this crate knows
     // - `Some(&str)` containing the member's string data
     //
     // Panics:
-    // - If the data is not valid UTF-8
-    // - If the section is malformed: end < start (invalid range), end > BUFFER_SIZE (out of bounds)
+    // - If the section is malformed, see `SectionError`.
     #[doc(hidden)]
     pub fn get_from_buffer<'a>(&self, buffer: &'a [u8; BUFFER_SIZE]) -> Option<&'a str> {
         let idx = *self as usize;
@@ -152,82 +257,12 @@ impl Member {
     // Takes usize instead of Member, to allow easy iteration in tests
     #[doc(hidden)]
     pub fn get_idx_from_buffer(idx: usize, buffer: &[u8; BUFFER_SIZE]) -> Option<&str> {
-        // Read the actual number of members from the first byte
-        let actual_num_members = Self::read_buffer_byte(buffer, 0) as usize;
-
-        // If first byte is 0, section is uninitialized (all zeros)
-        if actual_num_members == 0 {
-            return None;
-        }
-
-        // Forward compatibility: if requested member >= actual num_members, return None
-        if idx >= actual_num_members {
-            return None;
-        }
-
-        // Compute header size based on actual number of members in the section
-        let actual_header_size = header_size(actual_num_members);
-
-        // Read end offset for this member (stored at byte 1 + idx * 2, relative to header)
-        let end_offset_pos = 1 + idx * 2;
-        let end = actual_header_size + Self::read_buffer_u16(buffer, end_offset_pos) as usize;
-
-        // Calculate start: header_size + previous member's end, or header_size for member 0
-        let start = if idx == 0 {
-            actual_header_size
-        } else {
-            let prev_end_pos = 1 + (idx - 1) * 2;
-            actual_header_size + Self::read_buffer_u16(buffer, prev_end_pos) as usize
-        };
-
-        // If start == end, member is not present
-        if start == end {
-            return None;
-        }
-
-        // Validate range
-        if end < start {
-            panic!(
-                "ver-stub: invalid range for {:?}: start={}, end={}",
-                idx, start, end
-            );
+        match SectionReader::new(buffer).get_idx(idx) {
+            Ok(s) => s,
+            // A newer version of ver-stub-build wrote the section, act as if it wasn't patched
+            Err(SectionError::UnsupportedVersion { .. }) => None,
+            Err(e) => panic!("ver-stub: {e}"),
         }
-        if end > BUFFER_SIZE {
-            panic!(
-                "ver-stub: end offset {} exceeds buffer size {} for {:?}",
-                end, BUFFER_SIZE, idx
-            );
-        }
-
-        // Get the slice and convert to UTF-8.
-        // Use black_box to prevent the compiler from optimizing away the read,
-        // since the buffer is initialized to zeros at compile time, but changed at link time.
-        let bytes = core::hint::black_box(&buffer[start..end]);
-        match core::str::from_utf8(bytes) {
-            Ok(s) => Some(s),
-            Err(e) => panic!("ver-stub: invalid UTF-8 for {:?}: {:?}", idx, e),
-        }
-    }
-
-    // Reads a u16 from the buffer at the given offset (little-endian).
-    fn read_buffer_u16(buffer: &[u8; BUFFER_SIZE], offset: usize) -> u16 {
-        let lo = Self::read_buffer_byte(buffer, offset) as u16;
-        let hi = Self::read_buffer_byte(buffer, offset + 1) as u16;
-        lo | (hi << 8)
-    }
-
-    // Reads a byte from the buffer using volatile read to prevent optimization.
-    // This is necessary because the compiler would otherwise inline the zeros
-    // since the buffer is initialized to all zeros at compile time, and it isn't
-    // aware of the linker stuff that happens after.
-    #[inline(never)]
-    fn read_buffer_byte(buffer: &[u8; BUFFER_SIZE], offset: usize) -> u8 {
-        assert!(
-            offset < BUFFER_SIZE,
-            "ver-stub: invalid section data, {offset} >= {BUFFER_SIZE} is out of bounds"
-        );
-        // SAFETY: offset is bounds-checked by assert
-        unsafe { core::ptr::read_volatile(buffer.as_ptr().add(offset)) }
     }
 }
 
@@ -245,10 +280,19 @@ pub fn git_sha() -> Option<&'static str> {
 /// - Number of commits since that tag
 /// - Abbreviated commit hash
 /// - `-dirty` suffix if there are uncommitted changes
+///
+/// See [`git_describe_parsed`] to access these individually.
 pub fn git_describe() -> Option<&'static str> {
     Member::GitDescribe.get_from_buffer(&BUFFER)
 }
 
+/// Returns the git describe output split into its components, if present.
+///
+/// See [`Describe`] for how the output of `git describe` is interpreted.
+pub fn git_describe_parsed() -> Option<Describe<'static>> {
+    git_describe().and_then(Describe::parse)
+}
+
 /// Returns the git branch name, if present.
 ///
 /// This is the output of `git rev-parse --abbrev-ref HEAD`.
@@ -264,6 +308,11 @@ pub fn git_commit_timestamp() -> Option<&'static str> {
     Member::GitCommitTimestamp.get_from_buffer(&BUFFER)
 }
 
+/// Returns the git commit timestamp parsed as a [`Timestamp`], if present and valid.
+pub fn git_commit_timestamp_parsed() -> Option<Timestamp> {
+    git_commit_timestamp().and_then(Timestamp::parse)
+}
+
 /// Returns the git commit date, if present.
 ///
 /// This is the author date of HEAD formatted as a date only
@@ -288,6 +337,11 @@ pub fn build_timestamp() -> Option<&'static str> {
     Member::BuildTimestamp.get_from_buffer(&BUFFER)
 }
 
+/// Returns the build timestamp parsed as a [`Timestamp`], if present and valid.
+pub fn build_timestamp_parsed() -> Option<Timestamp> {
+    build_timestamp().and_then(Timestamp::parse)
+}
+
 /// Returns the build date, if present.
 ///
 /// This is the date the binary was built, formatted as YYYY-MM-DD
@@ -304,10 +358,76 @@ pub fn custom() -> Option<&'static str> {
     Member::Custom.get_from_buffer(&BUFFER)
 }
 
+/// Returns the custom string deserialized as a `T`, if present and valid.
+///
+/// The custom string is decoded as JSON, as written by
+/// `LinkSection::with_custom_serialized()`, without allocating. Strings in `T` can
+/// borrow from the section, e.g. `&'static str`, unless they contain characters which JSON
+/// escapes (quotes, backslashes, control characters); those can only be read into owned
+/// string types, up to 256 bytes.
+///
+/// Returns `None` if the custom string is absent (for instance if an [overflow policy]
+/// dropped it), or isn't a valid encoding of `T`.
+///
+/// [overflow policy]: https://docs.rs/ver-stub-build/latest/ver_stub_build/enum.OverflowPolicy.html
+#[cfg(feature = "serde")]
+pub fn custom_as<T: serde::Deserialize<'static>>() -> Option<T> {
+    custom().and_then(payload::from_json)
+}
+
+/// Returns the value of the named field, if present.
+///
+/// Set it using `LinkSection::with_field()` in your build script. If the same key was
+/// set more than once, the first value is returned.
+pub fn field(key: &str) -> Option<&'static str> {
+    fields().get(key)
+}
+
+/// Returns an iterator over all named fields, as `(key, value)` pairs.
+///
+/// The iterator is empty if no fields are present.
+pub fn fields() -> Fields<'static> {
+    Fields::new(Member::Fields.get_from_buffer(&BUFFER).unwrap_or_default())
+}
+
+/// Returns all version data at once.
+///
+/// This reads every member from the section; see [`VersionInfo`].
+pub fn info() -> VersionInfo {
+    VersionInfo {
+        git_sha: git_sha(),
+        git_describe: git_describe(),
+        git_branch: git_branch(),
+        git_commit_timestamp: git_commit_timestamp(),
+        git_commit_date: git_commit_date(),
+        git_commit_msg: git_commit_msg(),
+        build_timestamp: build_timestamp(),
+        build_date: build_date(),
+        custom: custom(),
+        fields: fields(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_member_all() {
+        for (idx, member) in Member::ALL.iter().enumerate() {
+            assert_eq!(*member as usize, idx);
+        }
+    }
+
+    // The section isn't patched in unit tests, so nothing is present
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_age_absent() {
+        assert_eq!(build_age(), None);
+        assert_eq!(commit_age(), None);
+        assert_eq!(is_older_than(core::time::Duration::ZERO), None);
+    }
+
     #[test]
     fn test_zeroes() {
         let buffer = [0u8; BUFFER_SIZE];
@@ -398,4 +518,181 @@ mod tests {
 
         Member::GitSha.get_from_buffer(&buffer);
     }
+
+    #[test]
+    fn test_reader_any_length() {
+        let bytes = [
+            3u8, 4u8, 0u8, 4u8, 0u8, 6u8, 0u8, b'a', b's', b'd', b'f', b'h', b'i',
+        ];
+        let reader = SectionReader::new(&bytes);
+
+        assert_eq!(reader.get(Member::GitSha), Ok(Some("asdf")));
+        assert_eq!(reader.get(Member::GitDescribe), Ok(None));
+        assert_eq!(reader.get(Member::GitBranch), Ok(Some("hi")));
+        for idx in 3..Member::COUNT {
+            assert_eq!(reader.get_idx(idx), Ok(None));
+        }
+        assert_eq!(reader.used_len(), Ok(bytes.len()));
+
+        // Zeros of any length read as absent
+        for idx in 0..Member::COUNT {
+            assert_eq!(SectionReader::new(&[0u8; 3]).get_idx(idx), Ok(None));
+        }
+        assert_eq!(SectionReader::new(&[0u8; 3]).used_len(), Ok(0));
+    }
+
+    #[test]
+    fn test_reader_errors() {
+        assert_eq!(
+            SectionReader::new(&[]).get(Member::GitSha),
+            Err(SectionError::TruncatedHeader {
+                header_size: 1,
+                len: 0
+            })
+        );
+        assert_eq!(
+            SectionReader::new(&[9u8, 0u8, 0u8]).get(Member::GitSha),
+            Err(SectionError::TruncatedHeader {
+                header_size: 19,
+                len: 3
+            })
+        );
+        assert_eq!(
+            SectionReader::new(&[2u8, 4u8, 0u8, 0u8, 0u8, b'a', b's', b'd', b'f'])
+                .get(Member::GitDescribe),
+            Err(SectionError::InvalidRange {
+                idx: 1,
+                start: 9,
+                end: 5
+            })
+        );
+        assert_eq!(
+            SectionReader::new(&[1u8, 4u8, 0u8, b'a', b's']).get(Member::GitSha),
+            Err(SectionError::OutOfBounds {
+                idx: 0,
+                end: 7,
+                len: 5
+            })
+        );
+        assert!(matches!(
+            SectionReader::new(&[1u8, 2u8, 0u8, 255u8, 255u8]).get(Member::GitSha),
+            Err(SectionError::InvalidUtf8 { idx: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_reader_v2() {
+        let mut bytes = [0u8; 32];
+        bytes[..8].copy_from_slice(&SECTION_MAGIC);
+        bytes[8..21].copy_from_slice(&[2u8, 2, 4, 0, 6, 0, b'a', b's', b'd', b'f', b'h', b'i', 0]);
+        let reader = SectionReader::new(&bytes);
+
+        assert_eq!(reader.format(), Ok(Some(SectionFormat::V2)));
+        assert_eq!(reader.header_len(), Ok(14));
+        assert_eq!(reader.get(Member::GitSha), Ok(Some("asdf")));
+        assert_eq!(reader.get(Member::GitDescribe), Ok(Some("hi")));
+        for idx in 2..Member::COUNT {
+            assert_eq!(reader.get_idx(idx), Ok(None));
+        }
+        assert_eq!(reader.used_len(), Ok(20));
+
+        // The same data without the magic and version is version 1
+        let reader = SectionReader::new(&bytes[9..]);
+        assert_eq!(reader.format(), Ok(Some(SectionFormat::V1)));
+        assert_eq!(reader.get(Member::GitSha), Ok(Some("asdf")));
+
+        bytes[8] = 1;
+        assert_eq!(
+            SectionReader::new(&bytes).get(Member::GitSha),
+            Err(SectionError::UnsupportedVersion { version: 1 })
+        );
+        assert_eq!(
+            SectionReader::new(&bytes[..8]).get(Member::GitSha),
+            Err(SectionError::TruncatedHeader {
+                header_size: 10,
+                len: 8
+            })
+        );
+        assert_eq!(SectionReader::new(&[0u8; 3]).format(), Ok(None));
+    }
+
+    #[test]
+    fn test_reader_v3() {
+        let mut bytes = [0u8; 32];
+        bytes[..8].copy_from_slice(&SECTION_MAGIC);
+        bytes[8..24].copy_from_slice(&[
+            3u8, 2, 4, 0, 0, 0, 6, 0, 0, 0, b'a', b's', b'd', b'f', b'h', b'i',
+        ]);
+        let reader = SectionReader::new(&bytes);
+
+        assert_eq!(reader.format(), Ok(Some(SectionFormat::V3)));
+        assert_eq!(reader.header_len(), Ok(18));
+        assert_eq!(reader.get(Member::GitSha), Ok(Some("asdf")));
+        assert_eq!(reader.get(Member::GitDescribe), Ok(Some("hi")));
+        assert_eq!(reader.get(Member::GitBranch), Ok(None));
+        assert_eq!(reader.used_len(), Ok(24));
+
+        // An end offset which doesn't fit in u16
+        bytes[12] = 1;
+        assert_eq!(
+            SectionReader::new(&bytes).get(Member::GitSha),
+            Err(SectionError::OutOfBounds {
+                idx: 0,
+                end: 18 + 0x1_0000 + 4,
+                len: 32
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_version_reads_absent() {
+        let mut buffer = [0u8; BUFFER_SIZE];
+        buffer[..8].copy_from_slice(&SECTION_MAGIC);
+        buffer[8..16].copy_from_slice(&[99u8, 1, 4, 0, b'a', b's', b'd', b'f']);
+
+        assert_eq!(
+            SectionReader::new(&buffer).get(Member::GitSha),
+            Err(SectionError::UnsupportedVersion { version: 99 })
+        );
+        for idx in 0..Member::COUNT {
+            assert!(Member::get_idx_from_buffer(idx, &buffer).is_none());
+        }
+    }
+
+    #[test]
+    fn test_reader_find() {
+        let mut image = [0u8; 64];
+        // A stray copy of the magic, which doesn't decode
+        image[4..12].copy_from_slice(&SECTION_MAGIC);
+        image[12] = 99;
+        image[30..38].copy_from_slice(&SECTION_MAGIC);
+        image[38..46].copy_from_slice(&[2u8, 1, 4, 0, b'a', b's', b'd', b'f']);
+
+        let reader = SectionReader::find(&image).unwrap();
+        assert_eq!(reader.as_bytes(), &image[30..46]);
+        assert_eq!(reader.get(Member::GitSha), Ok(Some("asdf")));
+
+        assert!(SectionReader::find(&image[..40]).is_none());
+        assert!(SectionReader::find(&[0u8; 64]).is_none());
+    }
+
+    #[test]
+    fn test_version_info_display() {
+        use crate::test_util::Buf;
+        use core::fmt::Write;
+
+        let info = VersionInfo {
+            git_sha: Some("asdf"),
+            git_branch: Some("master"),
+            ..Default::default()
+        };
+        let mut buf = Buf::new();
+        write!(buf, "{info}").unwrap();
+
+        let mut lines = buf.as_str().lines();
+        assert_eq!(lines.next(), Some("git sha:         asdf"));
+        assert_eq!(lines.next(), Some("git describe:    (not set)"));
+        assert_eq!(lines.next(), Some("git branch:      master"));
+        assert_eq!(lines.last(), Some("custom:          (not set)"));
+    }
 }
@@ -0,0 +1,54 @@
+//! Typed custom payloads, decoded from JSON.
+
+use serde::Deserialize;
+
+/// Size of the buffer in which strings containing JSON escapes are unescaped.
+const UNESCAPE_BUFFER_SIZE: usize = 256;
+
+/// Deserializes a value from the JSON written by `LinkSection::with_custom_serialized()`.
+///
+/// Strings without escapes are borrowed from `json`. Strings with escapes (quotes,
+/// backslashes, control characters) are unescaped into a stack buffer, so they can only
+/// be read into owned string types, and at most [`UNESCAPE_BUFFER_SIZE`] bytes long.
+///
+/// Returns `None` if `json` isn't a valid JSON encoding of `T`.
+pub(crate) fn from_json<'a, T: Deserialize<'a>>(json: &'a str) -> Option<T> {
+    let mut buffer = [0u8; UNESCAPE_BUFFER_SIZE];
+    serde_json_core::from_str_escaped(json, &mut buffer)
+        .ok()
+        .map(|(value, _len)| value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Deploy<'a> {
+        target: &'a str,
+        build_number: u32,
+        features: [bool; 2],
+    }
+
+    #[test]
+    fn test_from_json() {
+        let json = r#"{"target":"eu-west-1","build_number":42,"features":[true,false]}"#;
+        assert_eq!(
+            from_json(json),
+            Some(Deploy {
+                target: "eu-west-1",
+                build_number: 42,
+                features: [true, false],
+            })
+        );
+
+        // Missing fields, trailing data, and truncated payloads are rejected
+        assert_eq!(from_json::<Deploy>(r#"{"target":"x"}"#), None);
+        assert_eq!(from_json::<u32>("42 43"), None);
+        assert_eq!(from_json::<Deploy>(&json[..json.len() - 1]), None);
+
+        // Escaped strings can't be borrowed, but can be read as chars
+        assert_eq!(from_json::<&str>(r#""a\"b""#), None);
+        assert_eq!(from_json::<char>(r#""\n""#), Some('\n'));
+    }
+}
@@ -0,0 +1,353 @@
+//! Parsing of section data from arbitrary byte slices.
+
+use core::fmt;
+use core::str::Utf8Error;
+
+use crate::{Fields, Member, SECTION_MAGIC, SectionFormat};
+
+/// Error returned when section data is malformed.
+///
+/// The `idx` fields hold the index of the member that was being read
+/// (see [`Member`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SectionError {
+    /// The slice is too short to hold the header announced by its first bytes.
+    TruncatedHeader {
+        /// Header size implied by the format and the `num_members` byte.
+        header_size: usize,
+        /// Actual length of the slice.
+        len: usize,
+    },
+    /// The section starts with [`SECTION_MAGIC`], but its format version is not known
+    /// to this version of the crate.
+    UnsupportedVersion {
+        /// The format version byte.
+        version: u8,
+    },
+    /// A member's end offset is before its start offset.
+    InvalidRange {
+        /// Member index.
+        idx: usize,
+        /// Absolute start offset of the member.
+        start: usize,
+        /// Absolute end offset of the member.
+        end: usize,
+    },
+    /// A member's end offset is past the end of the slice.
+    OutOfBounds {
+        /// Member index.
+        idx: usize,
+        /// Absolute end offset of the member.
+        end: usize,
+        /// Actual length of the slice.
+        len: usize,
+    },
+    /// A member's data is not valid UTF-8.
+    InvalidUtf8 {
+        /// Member index.
+        idx: usize,
+        /// The underlying UTF-8 error.
+        error: Utf8Error,
+    },
+}
+
+impl fmt::Display for SectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TruncatedHeader { header_size, len } => write!(
+                f,
+                "invalid section data, header size {header_size} exceeds buffer size {len}"
+            ),
+            Self::UnsupportedVersion { version } => {
+                write!(f, "unsupported section format version {version}")
+            }
+            Self::InvalidRange { idx, start, end } => {
+                write!(f, "invalid range for {idx}: start={start}, end={end}")
+            }
+            Self::OutOfBounds { idx, end, len } => {
+                write!(f, "end offset {end} exceeds buffer size {len} for {idx}")
+            }
+            Self::InvalidUtf8 { idx, error } => write!(f, "invalid UTF-8 for {idx}: {error:?}"),
+        }
+    }
+}
+
+impl core::error::Error for SectionError {}
+
+/// Reads members out of a ver_stub section held in an arbitrary byte slice.
+///
+/// This uses the same decoding logic as the accessor functions of this crate,
+/// but works with slices of any length (e.g. a section extracted from a binary
+/// on disk), and reports malformed data as a [`SectionError`] instead of panicking.
+///
+/// ```
+/// use ver_stub::{Member, SectionReader};
+///
+/// let bytes = [1u8, 4, 0, b'a', b's', b'd', b'f'];
+/// let reader = SectionReader::new(&bytes);
+/// assert_eq!(reader.get(Member::GitSha), Ok(Some("asdf")));
+/// assert_eq!(reader.get(Member::GitBranch), Ok(None));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct SectionReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> SectionReader<'a> {
+    /// Creates a reader over the given section bytes.
+    pub const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /// Returns the underlying section bytes.
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Reads a member from the section.
+    ///
+    /// Returns `Ok(None)` if the member is not present.
+    pub fn get(&self, member: Member) -> Result<Option<&'a str>, SectionError> {
+        self.get_idx(member as usize)
+    }
+
+    /// Reads the named fields from the section.
+    ///
+    /// Returns an empty iterator if the `fields` member is not present.
+    pub fn fields(&self) -> Result<Fields<'a>, SectionError> {
+        Ok(Fields::new(self.get(Member::Fields)?.unwrap_or_default()))
+    }
+
+    /// Reads a member from the section by its index.
+    ///
+    /// This also allows reading members written by a newer version of
+    /// `ver-stub-build` which this version doesn't know about.
+    ///
+    /// Returns `Ok(None)` if the member is not present:
+    /// - The section is all zeros (never written)
+    /// - `idx` is not less than the number of members recorded in the section
+    /// - The member's start and end offsets are equal
+    pub fn get_idx(&self, idx: usize) -> Result<Option<&'a str>, SectionError> {
+        // If the section is uninitialized (all zeros), no member is present
+        let Some(header) = self.header()? else {
+            return Ok(None);
+        };
+
+        // Forward compatibility: if requested member >= actual num_members, return None
+        if idx >= header.num_members {
+            return Ok(None);
+        }
+
+        // Read end offset for this member (relative to header)
+        let end = header
+            .size
+            .saturating_add(self.read_end_offset(&header, idx));
+
+        // Calculate start: header_size + previous member's end, or header_size for member 0
+        let start = if idx == 0 {
+            header.size
+        } else {
+            header
+                .size
+                .saturating_add(self.read_end_offset(&header, idx - 1))
+        };
+
+        // If start == end, member is not present
+        if start == end {
+            return Ok(None);
+        }
+
+        // Validate range
+        if end < start {
+            return Err(SectionError::InvalidRange { idx, start, end });
+        }
+        if end > self.bytes.len() {
+            return Err(SectionError::OutOfBounds {
+                idx,
+                end,
+                len: self.bytes.len(),
+            });
+        }
+
+        // Get the slice and convert to UTF-8.
+        // Use black_box to prevent the compiler from optimizing away the read,
+        // since the buffer is initialized to zeros at compile time, but changed at link time.
+        let bytes = core::hint::black_box(&self.bytes[start..end]);
+        core::str::from_utf8(bytes)
+            .map(Some)
+            .map_err(|error| SectionError::InvalidUtf8 { idx, error })
+    }
+
+    /// Returns the number of bytes used by the header and member data.
+    ///
+    /// This is 0 if the section is all zeros (never written). The rest of the section,
+    /// up to its full length, is free space.
+    ///
+    /// ```
+    /// use ver_stub::SectionReader;
+    ///
+    /// let bytes = [1u8, 4, 0, b'a', b's', b'd', b'f', 0, 0];
+    /// assert_eq!(SectionReader::new(&bytes).used_len(), Ok(7));
+    /// ```
+    pub fn used_len(&self) -> Result<usize, SectionError> {
+        let Some(header) = self.header()? else {
+            return Ok(0);
+        };
+
+        // End offsets should be increasing, but check all of them in case the data is malformed
+        let mut used = header.size;
+        for idx in 0..header.num_members {
+            let end = header
+                .size
+                .saturating_add(self.read_end_offset(&header, idx));
+            if end > self.bytes.len() {
+                return Err(SectionError::OutOfBounds {
+                    idx,
+                    end,
+                    len: self.bytes.len(),
+                });
+            }
+            used = used.max(end);
+        }
+        Ok(used)
+    }
+
+    /// Returns the number of bytes used by the header, including the magic and the
+    /// format version if present.
+    ///
+    /// This is 0 if the section is all zeros (never written).
+    pub fn header_len(&self) -> Result<usize, SectionError> {
+        Ok(self.header()?.map_or(0, |header| header.size))
+    }
+
+    /// Returns the format of the section, or `None` if it is all zeros (never written).
+    ///
+    /// ```
+    /// use ver_stub::{SectionFormat, SectionReader};
+    ///
+    /// let bytes = [1u8, 4, 0, b'a', b's', b'd', b'f'];
+    /// assert_eq!(SectionReader::new(&bytes).format(), Ok(Some(SectionFormat::V1)));
+    /// ```
+    pub fn format(&self) -> Result<Option<SectionFormat>, SectionError> {
+        Ok(self.header()?.map(|header| header.format))
+    }
+
+    /// Finds a section in a raw image, such as the output of `objcopy -O binary` or a
+    /// core dump, by scanning it for [`SECTION_MAGIC`].
+    ///
+    /// Returns a reader over the header and member data of the first occurrence which
+    /// decodes successfully, so stray copies of the magic are skipped. Sections in
+    /// [`SectionFormat::V1`] have no magic, and can't be found this way.
+    ///
+    /// ```
+    /// use ver_stub::{Member, SECTION_MAGIC, SectionReader};
+    ///
+    /// let mut image = vec![0xAAu8; 100];
+    /// image.extend(SECTION_MAGIC);
+    /// image.extend([2u8, 1, 4, 0, b'a', b's', b'd', b'f']);
+    /// image.extend([0xAAu8; 100]);
+    ///
+    /// let reader = SectionReader::find(&image).unwrap();
+    /// assert_eq!(reader.get(Member::GitSha), Ok(Some("asdf")));
+    /// assert_eq!(reader.as_bytes().len(), 16);
+    /// ```
+    pub fn find(bytes: &'a [u8]) -> Option<Self> {
+        let mut pos = 0;
+        while let Some(found) = bytes[pos..]
+            .windows(SECTION_MAGIC.len())
+            .position(|window| window == SECTION_MAGIC)
+        {
+            let start = pos + found;
+            let reader = Self::new(&bytes[start..]);
+            if let Ok(Some(header)) = reader.header()
+                && (0..header.num_members).all(|idx| reader.get_idx(idx).is_ok())
+                && let Ok(used) = reader.used_len()
+            {
+                return Some(Self::new(&bytes[start..start + used]));
+            }
+            pos = start + 1;
+        }
+        None
+    }
+
+    // Reads the header, detecting the format.
+    // Returns None if the section is uninitialized (num_members is 0).
+    fn header(&self) -> Result<Option<Header>, SectionError> {
+        let len = self.bytes.len();
+        let has_magic =
+            (0..SECTION_MAGIC.len()).all(|i| self.read_byte(i) == Some(SECTION_MAGIC[i]));
+        let format = if has_magic {
+            let Some(version) = self.read_byte(SECTION_MAGIC.len()) else {
+                return Err(SectionError::TruncatedHeader {
+                    header_size: SectionFormat::V2.header_size(0),
+                    len,
+                });
+            };
+            match SectionFormat::from_version(version) {
+                // Version 1 has no magic
+                Some(SectionFormat::V1) | None => {
+                    return Err(SectionError::UnsupportedVersion { version });
+                }
+                Some(format) => format,
+            }
+        } else {
+            SectionFormat::V1
+        };
+
+        // An empty slice can't even hold the num_members byte
+        let Some(num_members) = self.read_byte(format.prefix_size()) else {
+            return Err(SectionError::TruncatedHeader {
+                header_size: format.header_size(0),
+                len,
+            });
+        };
+        let num_members = num_members as usize;
+        if num_members == 0 {
+            return Ok(None);
+        }
+
+        // Compute header size based on actual number of members in the section
+        let size = format.header_size(num_members);
+        if size > len {
+            return Err(SectionError::TruncatedHeader {
+                header_size: size,
+                len,
+            });
+        }
+        Ok(Some(Header {
+            format,
+            num_members,
+            size,
+        }))
+    }
+
+    // Reads the end offset of a member from the header (little-endian, relative to header).
+    // The caller must have checked that the header fits in the slice.
+    fn read_end_offset(&self, header: &Header, idx: usize) -> usize {
+        let size = header.format.offset_size();
+        let position = header.format.prefix_size() + 1 + idx * size;
+        (0..size).rev().fold(0, |value, i| {
+            value << 8 | self.read_byte(position + i).unwrap_or_default() as usize
+        })
+    }
+
+    // Reads a byte from the slice using volatile read to prevent optimization.
+    // This is necessary because the compiler would otherwise inline the zeros
+    // since the buffer is initialized to all zeros at compile time, and it isn't
+    // aware of the linker stuff that happens after.
+    #[inline(never)]
+    fn read_byte(&self, offset: usize) -> Option<u8> {
+        let byte = self.bytes.get(offset)?;
+        // SAFETY: the reference comes from a bounds-checked slice access
+        Some(unsafe { core::ptr::read_volatile(byte) })
+    }
+}
+
+// The header of a section which has been written.
+struct Header {
+    format: SectionFormat,
+    num_members: usize,
+    // Total size, member offsets are relative to the end of the header
+    size: usize,
+}
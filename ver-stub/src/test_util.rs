@@ -0,0 +1,34 @@
+//! Helpers shared by the unit tests.
+
+use core::fmt;
+
+/// Minimal fixed-size `fmt::Write` sink, since this crate is no_std.
+pub(crate) struct Buf {
+    bytes: [u8; 512],
+    len: usize,
+}
+
+impl Buf {
+    pub(crate) fn new() -> Self {
+        Self {
+            bytes: [0u8; 512],
+            len: 0,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap()
+    }
+}
+
+impl fmt::Write for Buf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.bytes
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
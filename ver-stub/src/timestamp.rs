@@ -0,0 +1,382 @@
+//! Parsing of the RFC 3339 timestamps stored in the version data.
+
+use core::fmt;
+
+/// An RFC 3339 timestamp, as stored by [`git_commit_timestamp`](crate::git_commit_timestamp)
+/// and [`build_timestamp`](crate::build_timestamp).
+///
+/// This keeps the UTC offset that was recorded (e.g. the author's timezone for git commits).
+/// Enable the `chrono` or `time` feature to convert it into the types of those crates.
+///
+/// ```
+/// use ver_stub::Timestamp;
+///
+/// let ts = Timestamp::parse("2023-11-15T03:43:20+05:30").unwrap();
+/// assert_eq!((ts.year(), ts.month(), ts.day()), (2023, 11, 15));
+/// assert_eq!(ts.utc_offset_seconds(), 5 * 3600 + 30 * 60);
+/// assert_eq!(ts.unix_seconds(), 1_700_000_000);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Timestamp {
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    nanosecond: u32,
+    utc_offset_seconds: i32,
+}
+
+impl Timestamp {
+    /// Parses an RFC 3339 timestamp, e.g. `2024-01-15T10:30:00+00:00`,
+    /// `2024-01-15T10:30:00.123Z`.
+    ///
+    /// Returns `None` if the string is not a valid RFC 3339 timestamp.
+    pub fn parse(s: &str) -> Option<Self> {
+        let b = s.as_bytes();
+        if b.len() < 20
+            || b[4] != b'-'
+            || b[7] != b'-'
+            || !matches!(b[10], b'T' | b't' | b' ')
+            || b[13] != b':'
+            || b[16] != b':'
+        {
+            return None;
+        }
+
+        let year = digits(&b[0..4])? as u16;
+        let month = digits(&b[5..7])? as u8;
+        let day = digits(&b[8..10])? as u8;
+        let hour = digits(&b[11..13])? as u8;
+        let minute = digits(&b[14..16])? as u8;
+        // 60 is a leap second
+        let second = digits(&b[17..19])? as u8;
+        if !(1..=12).contains(&month)
+            || day == 0
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 60
+        {
+            return None;
+        }
+
+        // Optional fractional seconds, digits beyond nanosecond precision are ignored
+        let mut rest = &b[19..];
+        let mut nanosecond = 0;
+        if let [b'.', frac @ ..] = rest {
+            let len = frac.iter().take_while(|c| c.is_ascii_digit()).count();
+            if len == 0 {
+                return None;
+            }
+            for (i, c) in frac[..len].iter().enumerate() {
+                if i < 9 {
+                    nanosecond = nanosecond * 10 + u32::from(c - b'0');
+                }
+            }
+            nanosecond *= 10u32.pow(9usize.saturating_sub(len) as u32);
+            rest = &frac[len..];
+        }
+
+        let utc_offset_seconds = match rest {
+            [b'Z' | b'z'] => 0,
+            [sign @ (b'+' | b'-'), hh @ .., b':', m1, m2] if hh.len() == 2 => {
+                let hours = digits(hh)? as i32;
+                let minutes = digits(&[*m1, *m2])? as i32;
+                if hours > 23 || minutes > 59 {
+                    return None;
+                }
+                let offset = hours * 3600 + minutes * 60;
+                if *sign == b'-' { -offset } else { offset }
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            nanosecond,
+            utc_offset_seconds,
+        })
+    }
+
+    /// Returns the year, in the recorded UTC offset.
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// Returns the month (1-12), in the recorded UTC offset.
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// Returns the day of the month (1-31), in the recorded UTC offset.
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// Returns the hour (0-23), in the recorded UTC offset.
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    /// Returns the minute (0-59).
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    /// Returns the second (0-60, where 60 is a leap second).
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
+    /// Returns the fractional part of the second, in nanoseconds.
+    pub fn nanosecond(&self) -> u32 {
+        self.nanosecond
+    }
+
+    /// Returns the UTC offset in seconds, positive east of UTC.
+    pub fn utc_offset_seconds(&self) -> i32 {
+        self.utc_offset_seconds
+    }
+
+    /// Returns the number of seconds since the unix epoch (1970-01-01T00:00:00Z).
+    ///
+    /// The fractional part of the second is discarded, and a leap second is
+    /// counted as the first second of the next minute.
+    pub fn unix_seconds(&self) -> i64 {
+        let days = days_from_civil(self.year.into(), self.month.into(), self.day.into());
+        days * 86400
+            + i64::from(self.hour) * 3600
+            + i64::from(self.minute) * 60
+            + i64::from(self.second)
+            - i64::from(self.utc_offset_seconds)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    /// Formats as RFC 3339, the same way `ver-stub-build` does.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )?;
+        // Like chrono, use the shortest of milli-, micro- or nanosecond precision
+        match self.nanosecond {
+            0 => {}
+            ns if ns % 1_000_000 == 0 => write!(f, ".{:03}", ns / 1_000_000)?,
+            ns if ns % 1_000 == 0 => write!(f, ".{:06}", ns / 1_000)?,
+            ns => write!(f, ".{:09}", ns)?,
+        }
+        let sign = if self.utc_offset_seconds < 0 {
+            '-'
+        } else {
+            '+'
+        };
+        let offset = self.utc_offset_seconds.unsigned_abs();
+        write!(f, "{}{:02}:{:02}", sign, offset / 3600, offset % 3600 / 60)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Timestamp> for std::time::SystemTime {
+    fn from(ts: Timestamp) -> Self {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let secs = ts.unix_seconds();
+        let whole = Duration::from_secs(secs.unsigned_abs());
+        let second = if secs >= 0 {
+            UNIX_EPOCH + whole
+        } else {
+            UNIX_EPOCH - whole
+        };
+        // The fractional part counts forward from the whole second, even before the epoch
+        second + Duration::from_nanos(ts.nanosecond.into())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<Timestamp> for chrono::DateTime<chrono::FixedOffset> {
+    fn from(ts: Timestamp) -> Self {
+        // chrono represents a leap second as second 59 with nanoseconds >= 1_000_000_000
+        let (second, nanosecond) = if ts.second == 60 {
+            (59, ts.nanosecond + 1_000_000_000)
+        } else {
+            (ts.second, ts.nanosecond)
+        };
+        // All fields were validated when parsing
+        let date = chrono::NaiveDate::from_ymd_opt(ts.year.into(), ts.month.into(), ts.day.into())
+            .expect("valid date");
+        let time = chrono::NaiveTime::from_hms_nano_opt(
+            ts.hour.into(),
+            ts.minute.into(),
+            second.into(),
+            nanosecond,
+        )
+        .expect("valid time");
+        let offset = chrono::FixedOffset::east_opt(ts.utc_offset_seconds).expect("valid offset");
+        chrono::NaiveDateTime::new(date, time)
+            .and_local_timezone(offset)
+            .single()
+            .expect("fixed offsets are unambiguous")
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<Timestamp> for time::OffsetDateTime {
+    /// Fails for leap seconds, which `time` can't represent.
+    type Error = time::error::ComponentRange;
+
+    fn try_from(ts: Timestamp) -> Result<Self, Self::Error> {
+        let month = time::Month::try_from(ts.month)?;
+        let date = time::Date::from_calendar_date(ts.year.into(), month, ts.day)?;
+        let time = time::Time::from_hms_nano(ts.hour, ts.minute, ts.second, ts.nanosecond)?;
+        let offset = time::UtcOffset::from_whole_seconds(ts.utc_offset_seconds)?;
+        Ok(time::PrimitiveDateTime::new(date, time).assume_offset(offset))
+    }
+}
+
+// Parses a fixed-width string of ASCII digits
+fn digits(b: &[u8]) -> Option<u32> {
+    b.iter().try_fold(0, |acc, c| {
+        c.is_ascii_digit().then(|| acc * 10 + u32::from(c - b'0'))
+    })
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar.
+// See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Buf;
+
+    #[test]
+    fn test_parse_timestamp() {
+        let ts = Timestamp::parse("2024-01-15T10:30:00+00:00").unwrap();
+        assert_eq!(
+            (
+                ts.year(),
+                ts.month(),
+                ts.day(),
+                ts.hour(),
+                ts.minute(),
+                ts.second()
+            ),
+            (2024, 1, 15, 10, 30, 0)
+        );
+        assert_eq!(ts.nanosecond(), 0);
+        assert_eq!(ts.utc_offset_seconds(), 0);
+        assert_eq!(ts.unix_seconds(), 1_705_314_600);
+
+        let ts = Timestamp::parse("2026-10-16T19:02:03.414128396+00:00").unwrap();
+        assert_eq!(ts.nanosecond(), 414_128_396);
+        assert_eq!(
+            Timestamp::parse("2026-10-16T19:02:03.5Z")
+                .unwrap()
+                .nanosecond(),
+            500_000_000
+        );
+
+        let ts = Timestamp::parse("1969-12-31T16:00:00-08:00").unwrap();
+        assert_eq!(ts.utc_offset_seconds(), -8 * 3600);
+        assert_eq!(ts.unix_seconds(), 0);
+
+        assert_eq!(
+            Timestamp::parse("2000-02-29T00:00:00Z")
+                .unwrap()
+                .unix_seconds(),
+            951_782_400
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamp_invalid() {
+        for s in [
+            "",
+            "2024-01-15",
+            "2024-01-15T10:30:00",
+            "2024-01-15T10:30:00+0000",
+            "2024-13-15T10:30:00Z",
+            "2023-02-29T10:30:00Z",
+            "2024-01-15T24:00:00Z",
+            "2024-01-15T10:30:00.Z",
+            "2024-01-15T10:30:00+24:00",
+            "2024-01-15T10:30:00Zjunk",
+            "+2024-01-15T10:30:00Z",
+        ] {
+            assert_eq!(Timestamp::parse(s), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn test_display_timestamp() {
+        for s in [
+            "2024-01-15T10:30:00+00:00",
+            "2024-01-15T10:30:00.123+05:30",
+            "2024-01-15T10:30:00.123456-01:00",
+            "2026-10-16T19:02:03.414128396+00:00",
+        ] {
+            let ts = Timestamp::parse(s).unwrap();
+            let mut buf = Buf::new();
+            fmt::write(&mut buf, format_args!("{ts}")).unwrap();
+            assert_eq!(buf.as_str(), s);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_timestamp_to_system_time() {
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        let ts = Timestamp::parse("2023-11-15T03:43:20.5+05:30").unwrap();
+        let t: SystemTime = ts.into();
+        assert_eq!(t, UNIX_EPOCH + Duration::from_millis(1_700_000_000_500));
+
+        let ts = Timestamp::parse("1969-12-31T23:59:59.25Z").unwrap();
+        let t: SystemTime = ts.into();
+        assert_eq!(t, UNIX_EPOCH - Duration::from_millis(750));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_timestamp_to_chrono() {
+        let s = "2024-01-15T10:30:00.123+05:30";
+        let dt: chrono::DateTime<chrono::FixedOffset> = Timestamp::parse(s).unwrap().into();
+        assert_eq!(dt, chrono::DateTime::parse_from_rfc3339(s).unwrap());
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_timestamp_to_time() {
+        let ts = Timestamp::parse("2024-01-15T10:30:00.123+05:30").unwrap();
+        let dt = time::OffsetDateTime::try_from(ts).unwrap();
+        assert_eq!(dt.unix_timestamp(), ts.unix_seconds());
+        assert_eq!(dt.nanosecond(), 123_000_000);
+    }
+}
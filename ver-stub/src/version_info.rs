@@ -0,0 +1,65 @@
+//! Aggregate view of all members of the version data.
+
+use core::fmt;
+
+use crate::Fields;
+
+/// All version data embedded in the binary, as returned by [`info()`](crate::info).
+///
+/// Each field is `None` if the corresponding member is not present, and `fields` is
+/// empty if there are no named fields.
+///
+/// The [`Display`](fmt::Display) implementation renders a multi-line,
+/// human-readable summary, with `(not set)` for absent members, followed by a
+/// `field <key>: <value>` line for each named field. Since this
+/// crate is `no_std`, it can be written to any [`fmt::Write`] sink.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct VersionInfo {
+    /// See [`git_sha`](crate::git_sha).
+    pub git_sha: Option<&'static str>,
+    /// See [`git_describe`](crate::git_describe).
+    pub git_describe: Option<&'static str>,
+    /// See [`git_branch`](crate::git_branch).
+    pub git_branch: Option<&'static str>,
+    /// See [`git_commit_timestamp`](crate::git_commit_timestamp).
+    pub git_commit_timestamp: Option<&'static str>,
+    /// See [`git_commit_date`](crate::git_commit_date).
+    pub git_commit_date: Option<&'static str>,
+    /// See [`git_commit_msg`](crate::git_commit_msg).
+    pub git_commit_msg: Option<&'static str>,
+    /// See [`build_timestamp`](crate::build_timestamp).
+    pub build_timestamp: Option<&'static str>,
+    /// See [`build_date`](crate::build_date).
+    pub build_date: Option<&'static str>,
+    /// See [`custom`](crate::custom).
+    pub custom: Option<&'static str>,
+    /// See [`fields`](crate::fields).
+    pub fields: Fields<'static>,
+}
+
+impl fmt::Display for VersionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = [
+            ("git sha:", self.git_sha),
+            ("git describe:", self.git_describe),
+            ("git branch:", self.git_branch),
+            ("git timestamp:", self.git_commit_timestamp),
+            ("git date:", self.git_commit_date),
+            ("git msg:", self.git_commit_msg),
+            ("build timestamp:", self.build_timestamp),
+            ("build date:", self.build_date),
+            ("custom:", self.custom),
+        ];
+        for (i, (label, value)) in lines.into_iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{label:<16} {}", value.unwrap_or("(not set)"))?;
+        }
+        for (key, value) in self.fields {
+            write!(f, "\nfield {key}: {value}")?;
+        }
+        Ok(())
+    }
+}